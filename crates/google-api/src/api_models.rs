@@ -1,13 +1,35 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
 use email_address::EmailAddress;
 use serde::{Deserialize, Serialize};
 
-use crate::models::UtcDateTime;
+use crate::models::{AclRole, ReminderMethod, UtcDateTime};
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EventListResponse {
     pub next_page_token: Option<String>,
-    pub items: Vec<EventResponse>,
+    pub items: Vec<RawEventResponse>,
+}
+
+/// Event together with the JSON it was parsed from, kept for snapshots.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "serde_json::Value")]
+pub struct RawEventResponse {
+    pub event: EventResponse,
+    pub json: serde_json::Value,
+}
+
+impl TryFrom<serde_json::Value> for RawEventResponse {
+    type Error = serde_json::Error;
+
+    fn try_from(json: serde_json::Value) -> Result<Self, Self::Error> {
+        Ok(RawEventResponse {
+            event: serde_json::from_value(json.clone())?,
+            json,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -16,15 +38,68 @@ pub struct EventResponse {
     pub id: String,
     pub summary: Option<String>,
     pub description: Option<String>,
+    pub location: Option<String>,
+    pub source: Option<SourceResponse>,
+    // cancelled events may come without times and creator
+    #[serde(default)]
     pub start: TimeResponse,
+    #[serde(default)]
     pub end: TimeResponse,
-    pub creator: CreatorResponse,
+    pub creator: Option<CreatorResponse>,
+    pub status: Option<String>,
+    pub updated: Option<UtcDateTime>,
+    pub extended_properties: Option<ExtendedProperties>,
+    pub color_id: Option<String>,
+    pub reminders: Option<Reminders>,
+    pub recurrence: Option<Vec<String>>,
+    pub recurring_event_id: Option<String>,
+    pub original_start_time: Option<TimeResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminders {
+    pub use_default: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<ReminderOverride>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderOverride {
+    pub method: ReminderMethod,
+    pub minutes: u32,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct ColorsResponse {
+    pub calendar: HashMap<String, ColorResponse>,
+    pub event: HashMap<String, ColorResponse>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorResponse {
+    pub background: String,
+    pub foreground: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtendedProperties {
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub private: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub shared: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TimeResponse {
+    pub date: Option<NaiveDate>,
     pub date_time: Option<UtcDateTime>,
+    pub time_zone: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -33,13 +108,32 @@ pub struct CreatorResponse {
     pub email: EmailAddress,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceResponse {
+    pub title: Option<String>,
+    pub url: String,
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EventPost {
     pub summary: String,
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourcePost>,
     pub start: TimePost,
     pub end: TimePost,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extended_properties: Option<ExtendedProperties>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reminders: Option<Reminders>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -50,13 +144,201 @@ pub struct EventPatch {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourcePost>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub start: Option<TimePost>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub end: Option<TimePost>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extended_properties: Option<ExtendedProperties>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reminders: Option<Reminders>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transparency: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourcePost {
+    pub title: String,
+    pub url: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TimePost {
-    pub date_time: UtcDateTime,
+    // both are always sent, so patching an event can switch between all-day and timed
+    pub date: Option<NaiveDate>,
+    pub date_time: Option<UtcDateTime>,
+    pub time_zone: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreeBusyPost {
+    pub time_min: UtcDateTime,
+    pub time_max: UtcDateTime,
+    pub items: Vec<FreeBusyItemPost>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreeBusyItemPost {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreeBusyResponse {
+    pub calendars: HashMap<String, FreeBusyCalendarResponse>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreeBusyCalendarResponse {
+    #[serde(default)]
+    pub busy: Vec<BusyResponse>,
+    #[serde(default)]
+    pub errors: Vec<ErrorItemResponse>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BusyResponse {
+    pub start: UtcDateTime,
+    pub end: UtcDateTime,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarListResponse {
+    pub next_page_token: Option<String>,
+    #[serde(default)]
+    pub items: Vec<CalendarListEntryResponse>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarListEntryResponse {
+    pub id: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub time_zone: Option<String>,
+    pub access_role: Option<AclRole>,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarResponse {
+    pub id: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub time_zone: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarPost {
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AclListResponse {
+    pub next_page_token: Option<String>,
+    #[serde(default)]
+    pub items: Vec<AclRuleResponse>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AclRuleResponse {
+    pub id: String,
+    pub role: AclRole,
+    pub scope: AclScopeBody,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AclRulePost {
+    pub role: AclRole,
+    pub scope: AclScopeBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AclScopeBody {
+    #[serde(rename = "type")]
+    pub scope_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchPost {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub channel_type: String,
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<WatchParams>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchParams {
+    /// Channel time to live in seconds.
+    pub ttl: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelResponse {
+    pub id: String,
+    pub resource_id: String,
+    pub token: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    pub expiration: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelStopPost {
+    pub id: String,
+    pub resource_id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorBody {
+    pub code: u16,
+    pub message: String,
+    #[serde(default)]
+    pub errors: Vec<ErrorItemResponse>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorItemResponse {
+    pub domain: Option<String>,
+    pub reason: String,
+    pub message: Option<String>,
 }
@@ -0,0 +1,61 @@
+use email_address::EmailAddress;
+use tokio::sync::Mutex;
+
+use crate::errors::GoogleClientError;
+use crate::jwt::JsonWebToken;
+use crate::models::AccessToken;
+use crate::oauth2_client::Oauth2Client;
+
+/// Service account credentials with the current access token.
+/// Refreshes are serialized so concurrent requests never fetch more than one new token.
+pub struct Authenticator {
+    email: EmailAddress,
+    state: Mutex<AuthState>,
+}
+
+struct AuthState {
+    jwt: JsonWebToken,
+    access_token: AccessToken,
+}
+
+impl Authenticator {
+    pub async fn new(jwt: JsonWebToken) -> Result<Self, GoogleClientError> {
+        let access_token = Oauth2Client::new(&jwt)?.get_token().await?;
+        Ok(Authenticator {
+            email: jwt.issuer().clone(),
+            state: Mutex::new(AuthState { jwt, access_token }),
+        })
+    }
+
+    pub fn email(&self) -> &EmailAddress {
+        &self.email
+    }
+
+    /// Returns a valid access token, refreshing it if it has expired.
+    pub async fn token(&self) -> Result<String, GoogleClientError> {
+        let mut state = self.state.lock().await;
+        if !state.access_token.is_valid() {
+            state.refresh().await?;
+        }
+        Ok(state.access_token.token.clone())
+    }
+
+    /// Refreshes the access token after `expired` was rejected by Google,
+    /// unless another task has already replaced it.
+    pub async fn refresh(&self, expired: &str) -> Result<String, GoogleClientError> {
+        let mut state = self.state.lock().await;
+        if state.access_token.token == expired {
+            state.refresh().await?;
+        }
+        Ok(state.access_token.token.clone())
+    }
+}
+
+impl AuthState {
+    async fn refresh(&mut self) -> Result<(), GoogleClientError> {
+        let jwt = self.jwt.clone().refresh();
+        self.access_token = Oauth2Client::new(&jwt)?.get_token().await?;
+        self.jwt = jwt;
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use color_eyre::eyre::eyre;
+use reqwest::StatusCode;
+
+use crate::api_models::{EventPatch, EventPost, RawEventResponse};
+use crate::errors::GoogleClientError;
+use crate::models::{GoogleEvent, GoogleEventPatch, GoogleEventPost};
+
+/// Maximum number of requests Google Calendar accepts in a single batch.
+pub const MAX_BATCH_SIZE: usize = 50;
+pub(crate) const BATCH_BOUNDARY: &str = "batch_google_calendar_utility";
+
+#[derive(Debug, Clone)]
+pub enum GoogleBatchRequest {
+    Insert(GoogleEventPost),
+    Patch {
+        event_id: String,
+        event: GoogleEventPatch,
+    },
+    Delete {
+        event_id: String,
+    },
+}
+
+/// Result of a single batch item: the created/updated event, or `None` for deletes.
+pub type GoogleBatchResult = Result<Option<GoogleEvent>, GoogleClientError>;
+
+#[derive(Debug, Clone, Default)]
+pub struct GoogleEventBatch {
+    pub(crate) requests: Vec<GoogleBatchRequest>,
+}
+
+impl GoogleEventBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    #[must_use]
+    pub fn insert(mut self, event: GoogleEventPost) -> Self {
+        self.requests.push(GoogleBatchRequest::Insert(event));
+        self
+    }
+    #[must_use]
+    pub fn patch(mut self, event_id: &str, event: GoogleEventPatch) -> Self {
+        self.requests.push(GoogleBatchRequest::Patch {
+            event_id: event_id.to_owned(),
+            event,
+        });
+        self
+    }
+    #[must_use]
+    pub fn delete(mut self, event_id: &str) -> Self {
+        self.requests.push(GoogleBatchRequest::Delete {
+            event_id: event_id.to_owned(),
+        });
+        self
+    }
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+    pub fn requests(&self) -> &[GoogleBatchRequest] {
+        &self.requests
+    }
+}
+
+/// Builds a `multipart/mixed` batch body. `events_path` is the events collection path,
+/// e.g. `/calendar/v3/calendars/<id>/events/`.
+pub(crate) fn encode_batch(
+    events_path: &str,
+    requests: &[GoogleBatchRequest],
+) -> Result<String, GoogleClientError> {
+    let mut body = String::new();
+    for (index, request) in requests.iter().enumerate() {
+        let (method, path, json) = match request {
+            GoogleBatchRequest::Insert(event) => (
+                "POST",
+                events_path.to_owned(),
+                Some(to_json(&EventPost::from(event))?),
+            ),
+            GoogleBatchRequest::Patch { event_id, event } => (
+                "PATCH",
+                format!("{events_path}{event_id}"),
+                Some(to_json(&EventPatch::from(event))?),
+            ),
+            GoogleBatchRequest::Delete { event_id } => {
+                ("DELETE", format!("{events_path}{event_id}"), None)
+            }
+        };
+        write!(
+            body,
+            "--{BATCH_BOUNDARY}\r\nContent-Type: application/http\r\nContent-ID: <item{index}>\r\n\r\n{method} {path} HTTP/1.1\r\n"
+        )
+        .unwrap();
+        match json {
+            Some(json) => write!(
+                body,
+                "Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{json}\r\n",
+                json.len()
+            )
+            .unwrap(),
+            None => body.push_str("\r\n"),
+        }
+    }
+    write!(body, "--{BATCH_BOUNDARY}--\r\n").unwrap();
+    Ok(body)
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, GoogleClientError> {
+    serde_json::to_string(value).map_err(|err| GoogleClientError::Other { error: err.into() })
+}
+
+/// Parses a `multipart/mixed` batch response into per-item results, in request order.
+pub(crate) fn decode_batch(
+    content_type: &str,
+    body: &str,
+    len: usize,
+) -> Result<Vec<GoogleBatchResult>, GoogleClientError> {
+    let boundary = content_type
+        .split(';')
+        .find_map(|x| x.trim().strip_prefix("boundary="))
+        .map(|x| x.trim_matches('"'))
+        .ok_or(GoogleClientError::Other {
+            error: eyre!("Batch response has no boundary: {content_type}"),
+        })?;
+    let body = body.replace("\r\n", "\n");
+    let mut results: HashMap<usize, GoogleBatchResult> = HashMap::new();
+    for part in body.split(&format!("--{boundary}")).skip(1) {
+        if part.starts_with("--") {
+            break;
+        }
+        let (part_headers, http_response) =
+            part.trim_start().split_once("\n\n").unwrap_or_default();
+        let index = part_headers
+            .lines()
+            .find_map(|x| {
+                let (name, value) = x.split_once(':')?;
+                name.eq_ignore_ascii_case("Content-ID").then_some(value)
+            })
+            .and_then(|x| {
+                x.trim()
+                    .trim_start_matches("<response-item")
+                    .trim_end_matches('>')
+                    .parse::<usize>()
+                    .ok()
+            });
+        if let Some(index) = index {
+            results.insert(index, decode_item(http_response));
+        }
+    }
+    Ok((0..len)
+        .map(|index| {
+            results.remove(&index).unwrap_or_else(|| {
+                Err(GoogleClientError::Other {
+                    error: eyre!("Batch response is missing item {index}"),
+                })
+            })
+        })
+        .collect())
+}
+
+fn decode_item(http_response: &str) -> GoogleBatchResult {
+    let (head, body) = http_response
+        .split_once("\n\n")
+        .unwrap_or((http_response, ""));
+    let status = head
+        .lines()
+        .next()
+        .and_then(|x| x.split_whitespace().nth(1))
+        .and_then(|x| x.parse::<u16>().ok())
+        .and_then(|x| StatusCode::from_u16(x).ok())
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let body = body.trim();
+    if !status.is_success() {
+        return Err(GoogleClientError::from_body(status, body));
+    }
+    if body.is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str::<RawEventResponse>(body)
+        .map(|x| Some(x.into()))
+        .map_err(|err| GoogleClientError::Other { error: err.into() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT_TYPE: &str = "multipart/mixed; boundary=batch_abc";
+
+    fn part(index: usize, http_response: &str) -> String {
+        format!(
+            "--batch_abc\r\nContent-Type: application/http\r\nContent-ID: <response-item{index}>\r\n\r\n{http_response}\r\n"
+        )
+    }
+
+    #[test]
+    fn decodes_items_by_content_id() {
+        let body = [
+            part(
+                1,
+                "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n",
+            ),
+            part(
+                0,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"id\": \"abc\", \"summary\": \"Yoga\"}",
+            ),
+            "--batch_abc--\r\n".to_owned(),
+        ]
+        .concat();
+        let results = decode_batch(CONTENT_TYPE, &body, 2).unwrap();
+        let event = results[0].as_ref().unwrap().as_ref().unwrap();
+        assert_eq!(event.id, "abc");
+        assert_eq!(event.summary.as_deref(), Some("Yoga"));
+        assert!(matches!(results[1], Ok(None)));
+    }
+
+    #[test]
+    fn decodes_item_errors_and_missing_items() {
+        let error = r#"{"error": {"code": 404, "message": "Not Found", "errors": [{"reason": "notFound", "message": "Not Found"}]}}"#;
+        let body = [
+            part(
+                0,
+                &format!("HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\n\r\n{error}"),
+            ),
+            "--batch_abc--\r\n".to_owned(),
+        ]
+        .concat();
+        let results = decode_batch(CONTENT_TYPE, &body, 2).unwrap();
+        assert!(matches!(
+            results[0],
+            Err(GoogleClientError::NotFound { .. })
+        ));
+        assert!(matches!(results[1], Err(GoogleClientError::Other { .. })));
+    }
+
+    #[test]
+    fn requires_a_boundary() {
+        assert!(decode_batch("multipart/mixed", "", 0).is_err());
+    }
+}
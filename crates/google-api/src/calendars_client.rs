@@ -0,0 +1,200 @@
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use reqwest::Url;
+use reqwest::{Client, ClientBuilder, Response};
+use urlencoding::encode;
+
+use crate::api_models::{
+    AclListResponse, AclRulePost, AclRuleResponse, CalendarListResponse, CalendarPost,
+    CalendarResponse,
+};
+use crate::errors::{GoogleClientError, ToGoogleClientError};
+use crate::models::{GoogleAclRule, GoogleCalendar};
+
+const GOOGLE_CALENDAR_API_URL: &str = "https://www.googleapis.com/calendar/v3/";
+const CALENDAR_LIST_PATH: &str = "users/me/calendarList";
+const CALENDARS_PATH: &str = "calendars";
+
+/// Calendars of the authenticated account and their access control lists.
+#[derive(Debug, Clone)]
+pub struct GoogleCalendarsClient {
+    client: Client,
+    base_url: Url,
+}
+
+impl GoogleCalendarsClient {
+    pub fn new() -> Result<Self, GoogleClientError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+
+        let client = ClientBuilder::new().default_headers(headers).build()?;
+        Ok(Self {
+            client,
+            base_url: Url::parse(GOOGLE_CALENDAR_API_URL)?,
+        })
+    }
+
+    fn calendar_url(&self, calendar_id: &str) -> Result<Url, GoogleClientError> {
+        Ok(self
+            .base_url
+            .join(&format!("{CALENDARS_PATH}/{}", encode(calendar_id)))?)
+    }
+
+    fn acl_url(&self, calendar_id: &str) -> Result<Url, GoogleClientError> {
+        Ok(self
+            .base_url
+            .join(&format!("{CALENDARS_PATH}/{}/acl/", encode(calendar_id)))?)
+    }
+
+    pub(crate) async fn list_calendars(
+        &self,
+        token: &str,
+    ) -> Result<Vec<GoogleCalendar>, GoogleClientError> {
+        let mut calendars: Vec<GoogleCalendar> = Vec::new();
+        let mut next_page_token: Option<String> = None;
+        loop {
+            let mut request = self
+                .client
+                .get(self.base_url.join(CALENDAR_LIST_PATH)?)
+                .bearer_auth(token);
+            if let Some(page_token) = &next_page_token {
+                request = request.query(&[("pageToken", page_token)]);
+            }
+            let response = request
+                .send()
+                .await?
+                .map_error()
+                .await?
+                .json::<CalendarListResponse>()
+                .await?;
+
+            next_page_token = response.next_page_token.clone();
+            calendars.extend(response.items.into_iter().map(Into::into));
+            if next_page_token.is_none() {
+                break;
+            }
+        }
+        Ok(calendars)
+    }
+
+    pub(crate) async fn get_calendar(
+        &self,
+        token: &str,
+        calendar_id: &str,
+    ) -> Result<GoogleCalendar, GoogleClientError> {
+        let response = self
+            .client
+            .get(self.calendar_url(calendar_id)?)
+            .bearer_auth(token)
+            .send()
+            .await?
+            .map_error()
+            .await?
+            .json::<CalendarResponse>()
+            .await?;
+        Ok(response.into())
+    }
+
+    pub(crate) async fn create_calendar(
+        &self,
+        token: &str,
+        calendar: &CalendarPost,
+    ) -> Result<GoogleCalendar, GoogleClientError> {
+        let response = self
+            .client
+            .post(self.base_url.join(CALENDARS_PATH)?)
+            .bearer_auth(token)
+            .json(calendar)
+            .send()
+            .await?
+            .map_error()
+            .await?
+            .json::<CalendarResponse>()
+            .await?;
+        Ok(response.into())
+    }
+
+    pub(crate) async fn delete_calendar(
+        &self,
+        token: &str,
+        calendar_id: &str,
+    ) -> Result<Response, GoogleClientError> {
+        self.client
+            .delete(self.calendar_url(calendar_id)?)
+            .bearer_auth(token)
+            .send()
+            .await?
+            .map_error()
+            .await
+    }
+
+    pub(crate) async fn list_acl(
+        &self,
+        token: &str,
+        calendar_id: &str,
+    ) -> Result<Vec<GoogleAclRule>, GoogleClientError> {
+        let mut rules: Vec<GoogleAclRule> = Vec::new();
+        let mut next_page_token: Option<String> = None;
+        loop {
+            let mut request = self
+                .client
+                .get(self.acl_url(calendar_id)?)
+                .bearer_auth(token);
+            if let Some(page_token) = &next_page_token {
+                request = request.query(&[("pageToken", page_token)]);
+            }
+            let response = request
+                .send()
+                .await?
+                .map_error()
+                .await?
+                .json::<AclListResponse>()
+                .await?;
+
+            next_page_token = response.next_page_token.clone();
+            for rule in response.items {
+                rules.push(rule.try_into()?);
+            }
+            if next_page_token.is_none() {
+                break;
+            }
+        }
+        Ok(rules)
+    }
+
+    pub(crate) async fn insert_acl(
+        &self,
+        token: &str,
+        calendar_id: &str,
+        rule: &AclRulePost,
+        send_notifications: bool,
+    ) -> Result<GoogleAclRule, GoogleClientError> {
+        let response = self
+            .client
+            .post(self.acl_url(calendar_id)?)
+            .bearer_auth(token)
+            .query(&[("sendNotifications", send_notifications)])
+            .json(rule)
+            .send()
+            .await?
+            .map_error()
+            .await?
+            .json::<AclRuleResponse>()
+            .await?;
+        response.try_into()
+    }
+
+    pub(crate) async fn delete_acl(
+        &self,
+        token: &str,
+        calendar_id: &str,
+        rule_id: &str,
+    ) -> Result<Response, GoogleClientError> {
+        self.client
+            .delete(self.acl_url(calendar_id)?.join(&encode(rule_id))?)
+            .bearer_auth(token)
+            .send()
+            .await?
+            .map_error()
+            .await
+    }
+}
@@ -1,19 +1,108 @@
+use async_trait::async_trait;
+use color_eyre::eyre::eyre;
 use color_eyre::Report as AnyError;
 use reqwest::{Response, StatusCode};
 use thiserror::Error;
 
+use crate::api_models::ErrorResponse;
+
 #[derive(Error, Debug)]
 pub enum GoogleClientError {
     #[error("Error creating JWT: {error}")]
     JWT { error: JWTError },
     #[error("Access token has expired.")]
     TokenExpired,
-    #[error("Event with id \"{id}\" does not exist.")]
-    NotFound { id: String },
+    #[error("Not found: {message}")]
+    NotFound { message: String },
+    #[error("Resource is gone: {message}")]
+    Gone { message: String },
+    #[error("Rate limit exceeded: {message}")]
+    RateLimitExceeded { message: String },
+    #[error("Quota exceeded: {message}")]
+    QuotaExceeded { message: String },
+    #[error("Forbidden: {message}")]
+    Forbidden { message: String },
+    #[error("Duplicate: {message}")]
+    Duplicate { message: String },
+    #[error("Precondition failed: {message}")]
+    ConditionNotMet { message: String },
+    #[error("Google backend error: {message}")]
+    BackendError { message: String },
+    #[error("Google API error {status} ({reason}): {message}")]
+    Api {
+        status: u16,
+        reason: String,
+        message: String,
+    },
     #[error("Unexpected error: {error}.")]
     Other { error: AnyError },
 }
 
+impl GoogleClientError {
+    /// Whether repeating the same request later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            GoogleClientError::RateLimitExceeded { .. } | GoogleClientError::BackendError { .. }
+        )
+    }
+
+    /// Decodes Google's JSON error body, falling back to the status code
+    /// for responses that do not follow the API error format (e.g. OAuth2).
+    pub(crate) fn from_body(status: StatusCode, body: &str) -> Self {
+        match serde_json::from_str::<ErrorResponse>(body) {
+            Ok(response) => Self::from_error_response(status, response),
+            Err(_) => Self::from_status(status, body.to_owned()),
+        }
+    }
+
+    fn from_error_response(status: StatusCode, response: ErrorResponse) -> Self {
+        let message = response.error.message;
+        let Some(reason) = response.error.errors.first().map(|x| x.reason.clone()) else {
+            return Self::from_status(status, message);
+        };
+        match reason.as_str() {
+            "authError" => GoogleClientError::TokenExpired,
+            "notFound" => GoogleClientError::NotFound { message },
+            "deleted" | "fullSyncRequired" | "updatedMinTooLongAgo" => {
+                GoogleClientError::Gone { message }
+            }
+            "rateLimitExceeded" | "userRateLimitExceeded" => {
+                GoogleClientError::RateLimitExceeded { message }
+            }
+            "quotaExceeded" | "dailyLimitExceeded" => GoogleClientError::QuotaExceeded { message },
+            "forbidden"
+            | "forbiddenForNonOrganizer"
+            | "insufficientPermissions"
+            | "requiredAccessLevel" => GoogleClientError::Forbidden { message },
+            "duplicate" => GoogleClientError::Duplicate { message },
+            "conditionNotMet" => GoogleClientError::ConditionNotMet { message },
+            "backendError" => GoogleClientError::BackendError { message },
+            _ => GoogleClientError::Api {
+                status: status.as_u16(),
+                reason,
+                message,
+            },
+        }
+    }
+
+    fn from_status(status: StatusCode, message: String) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => GoogleClientError::TokenExpired,
+            StatusCode::NOT_FOUND => GoogleClientError::NotFound { message },
+            StatusCode::GONE => GoogleClientError::Gone { message },
+            StatusCode::TOO_MANY_REQUESTS => GoogleClientError::RateLimitExceeded { message },
+            StatusCode::FORBIDDEN => GoogleClientError::Forbidden { message },
+            StatusCode::CONFLICT => GoogleClientError::Duplicate { message },
+            StatusCode::PRECONDITION_FAILED => GoogleClientError::ConditionNotMet { message },
+            status if status.is_server_error() => GoogleClientError::BackendError { message },
+            _ => GoogleClientError::Other {
+                error: eyre!("{status}: {message}"),
+            },
+        }
+    }
+}
+
 impl From<reqwest::Error> for GoogleClientError {
     fn from(value: reqwest::Error) -> Self {
         GoogleClientError::Other {
@@ -30,24 +119,22 @@ impl From<url::ParseError> for GoogleClientError {
     }
 }
 
+#[async_trait]
 pub trait ToGoogleClientError {
-    fn map_error(self) -> Result<Self, GoogleClientError>
+    async fn map_error(self) -> Result<Self, GoogleClientError>
     where
         Self: Sized;
 }
 
+#[async_trait]
 impl ToGoogleClientError for Response {
-    fn map_error(self) -> Result<Self, GoogleClientError> {
-        if let Err(err) = self.error_for_status_ref() {
-            let url = self.url().to_string();
-            let id = url.split('/').last().unwrap_or_default();
-            return match self.status() {
-                StatusCode::UNAUTHORIZED => Err(GoogleClientError::TokenExpired),
-                StatusCode::NOT_FOUND => Err(GoogleClientError::NotFound { id: id.to_string() }),
-                _ => Err(GoogleClientError::Other { error: err.into() }),
-            };
+    async fn map_error(self) -> Result<Self, GoogleClientError> {
+        let status = self.status();
+        if !status.is_client_error() && !status.is_server_error() {
+            return Ok(self);
         }
-        Ok(self)
+        let body = self.text().await?;
+        Err(GoogleClientError::from_body(status, &body))
     }
 }
 
@@ -64,3 +151,149 @@ impl From<JWTError> for GoogleClientError {
         GoogleClientError::JWT { error: value }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Check = fn(&GoogleClientError) -> bool;
+
+    fn body(code: u16, reason: &str, message: &str) -> String {
+        format!(
+            r#"{{"error": {{"errors": [{{"domain": "global", "reason": "{reason}", "message": "{message}"}}], "code": {code}, "message": "{message}"}}}}"#
+        )
+    }
+
+    fn decode(code: u16, body: &str) -> GoogleClientError {
+        GoogleClientError::from_body(StatusCode::from_u16(code).unwrap(), body)
+    }
+
+    #[test]
+    fn decodes_reasons() {
+        let cases: [(u16, &str, &str, Check); 12] = [
+            (401, "authError", "Invalid Credentials", |x| {
+                matches!(x, GoogleClientError::TokenExpired)
+            }),
+            (404, "notFound", "Not Found", |x| {
+                matches!(x, GoogleClientError::NotFound { .. })
+            }),
+            (410, "deleted", "Resource has been deleted", |x| {
+                matches!(x, GoogleClientError::Gone { .. })
+            }),
+            (
+                410,
+                "updatedMinTooLongAgo",
+                "The requested minimum modification time lies too far in the past.",
+                |x| matches!(x, GoogleClientError::Gone { .. }),
+            ),
+            (403, "rateLimitExceeded", "Rate Limit Exceeded", |x| {
+                matches!(x, GoogleClientError::RateLimitExceeded { .. })
+            }),
+            (
+                429,
+                "userRateLimitExceeded",
+                "User Rate Limit Exceeded",
+                |x| matches!(x, GoogleClientError::RateLimitExceeded { .. }),
+            ),
+            (
+                403,
+                "quotaExceeded",
+                "Calendar usage limits exceeded.",
+                |x| matches!(x, GoogleClientError::QuotaExceeded { .. }),
+            ),
+            (
+                403,
+                "forbiddenForNonOrganizer",
+                "Shared properties can only be changed by the organizer of the event.",
+                |x| matches!(x, GoogleClientError::Forbidden { .. }),
+            ),
+            (
+                409,
+                "duplicate",
+                "The requested identifier already exists.",
+                |x| matches!(x, GoogleClientError::Duplicate { .. }),
+            ),
+            (412, "conditionNotMet", "Precondition Failed", |x| {
+                matches!(x, GoogleClientError::ConditionNotMet { .. })
+            }),
+            (503, "backendError", "Backend Error", |x| {
+                matches!(x, GoogleClientError::BackendError { .. })
+            }),
+            (400, "invalid", "Invalid resource id value.", |x| {
+                matches!(x, GoogleClientError::Api { status: 400, reason, message }
+                    if reason == "invalid" && message == "Invalid resource id value.")
+            }),
+        ];
+        for (code, reason, message, expected) in cases {
+            let error = decode(code, &body(code, reason, message));
+            assert!(expected(&error), "{reason}: {error:?}");
+        }
+    }
+
+    #[test]
+    fn keeps_messages() {
+        let error = decode(
+            403,
+            &body(403, "quotaExceeded", "Calendar usage limits exceeded."),
+        );
+        assert_eq!(
+            error.to_string(),
+            "Quota exceeded: Calendar usage limits exceeded."
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_status() {
+        let cases: [(u16, &str, Check); 7] = [
+            // OAuth2 token endpoint
+            (
+                400,
+                r#"{"error": "invalid_grant", "error_description": "Invalid JWT Signature."}"#,
+                |x| matches!(x, GoogleClientError::Other { .. }),
+            ),
+            (401, "", |x| matches!(x, GoogleClientError::TokenExpired)),
+            (
+                404,
+                "",
+                |x| matches!(x, GoogleClientError::NotFound { message } if message.is_empty()),
+            ),
+            (
+                429,
+                "Too Many Requests",
+                |x| matches!(x, GoogleClientError::RateLimitExceeded { message } if message == "Too Many Requests"),
+            ),
+            (
+                502,
+                "<html><title>Error 502 (Server Error)!!1</title></html>",
+                |x| matches!(x, GoogleClientError::BackendError { .. }),
+            ),
+            // no reasons
+            (
+                409,
+                r#"{"error": {"code": 409, "message": "Conflict"}}"#,
+                |x| matches!(x, GoogleClientError::Duplicate { message } if message == "Conflict"),
+            ),
+            (
+                400,
+                r#"{"error": {"code": 400, "message": "Bad Request", "errors": []}}"#,
+                |x| matches!(x, GoogleClientError::Other { .. }),
+            ),
+        ];
+        for (code, body, expected) in cases {
+            let error = decode(code, body);
+            assert!(expected(&error), "{code} {body}: {error:?}");
+        }
+    }
+
+    #[test]
+    fn retries_only_transient_errors() {
+        assert!(decode(403, &body(403, "rateLimitExceeded", "Rate Limit Exceeded")).is_retryable());
+        assert!(decode(500, "").is_retryable());
+        assert!(!decode(
+            403,
+            &body(403, "quotaExceeded", "Calendar usage limits exceeded.")
+        )
+        .is_retryable());
+        assert!(!decode(404, "").is_retryable());
+    }
+}
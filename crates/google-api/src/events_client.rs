@@ -1,19 +1,45 @@
+use std::collections::HashMap;
+
 use reqwest::header::{self, HeaderMap, HeaderValue};
 use reqwest::Url;
 use reqwest::{Client, ClientBuilder, Response};
+use urlencoding::encode;
 
-use crate::api_models::{EventListResponse, EventPatch, EventPost, EventResponse};
+use crate::api_models::{
+    ChannelResponse, ChannelStopPost, ColorsResponse, EventListResponse, EventPatch, EventPost,
+    FreeBusyPost, FreeBusyResponse, RawEventResponse, WatchPost,
+};
+use crate::batch::{
+    decode_batch, encode_batch, GoogleBatchRequest, GoogleBatchResult, BATCH_BOUNDARY,
+};
 use crate::errors::{GoogleClientError, ToGoogleClientError};
-use crate::models::{GoogleEvent, UtcDateTime};
+use crate::models::{
+    GoogleChannel, GoogleColors, GoogleEvent, GoogleEventListParams, GoogleEventSnapshot,
+    GoogleFreeBusy, UtcDateTime,
+};
 
-const PAGE_SIZE: &str = "15";
 const GOOGLE_API_URL: &str = "https://www.googleapis.com";
+const GOOGLE_BATCH_PATH: &str = "/batch/calendar/v3";
+const GOOGLE_COLORS_PATH: &str = "/calendar/v3/colors";
+const GOOGLE_FREE_BUSY_PATH: &str = "/calendar/v3/freeBusy";
+const GOOGLE_CHANNELS_STOP_PATH: &str = "/calendar/v3/channels/stop";
+
+fn events_url(calendar_id: &str) -> Result<Url, GoogleClientError> {
+    Ok(Url::parse(GOOGLE_API_URL)?.join(&format!(
+        "/calendar/v3/calendars/{}/events/",
+        encode(calendar_id)
+    ))?)
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GoogleEventsClient {
     client: Client,
+    calendar_id: String,
     base_url: Url,
-    pub(crate) auth_headers: HeaderMap,
+    batch_url: Url,
+    colors_url: Url,
+    free_busy_url: Url,
+    channels_stop_url: Url,
 }
 
 impl GoogleEventsClient {
@@ -24,61 +50,91 @@ impl GoogleEventsClient {
         let client = ClientBuilder::new().default_headers(headers).build()?;
         Ok(Self {
             client,
-            base_url: Url::parse(GOOGLE_API_URL)?
-                .join(&format!("/calendar/v3/calendars/{calendar_id}/events/"))?,
-            auth_headers: HeaderMap::new(),
+            calendar_id: calendar_id.to_owned(),
+            base_url: events_url(calendar_id)?,
+            batch_url: Url::parse(GOOGLE_API_URL)?.join(GOOGLE_BATCH_PATH)?,
+            colors_url: Url::parse(GOOGLE_API_URL)?.join(GOOGLE_COLORS_PATH)?,
+            free_busy_url: Url::parse(GOOGLE_API_URL)?.join(GOOGLE_FREE_BUSY_PATH)?,
+            channels_stop_url: Url::parse(GOOGLE_API_URL)?.join(GOOGLE_CHANNELS_STOP_PATH)?,
         })
     }
 
-    pub(crate) fn update_auth_headers(&mut self, token: &str) {
-        let mut headers = HeaderMap::new();
-        let mut basic_auth = HeaderValue::from_str(&format!("Bearer {token}")).unwrap();
-        basic_auth.set_sensitive(true);
-        headers.insert(header::AUTHORIZATION, basic_auth);
-        self.auth_headers = headers;
+    /// Same client working with the events of another calendar.
+    pub fn with_calendar(&self, calendar_id: &str) -> Result<Self, GoogleClientError> {
+        Ok(Self {
+            calendar_id: calendar_id.to_owned(),
+            base_url: events_url(calendar_id)?,
+            ..self.clone()
+        })
     }
 
+    pub fn calendar_id(&self) -> &str {
+        &self.calendar_id
+    }
+
+    /// One page of events, with the token of the next page if there is one.
     pub(crate) async fn list_events(
         &self,
-        search_param: Option<String>,
+        token: &str,
+        params: &GoogleEventListParams,
+        page_token: Option<&str>,
+    ) -> Result<(Vec<GoogleEvent>, Option<String>), GoogleClientError> {
+        let mut query_params = params.query();
+        if let Some(page_token) = page_token {
+            query_params.push(("pageToken", page_token.to_owned()));
+        }
+        let response = self
+            .client
+            .get(self.base_url.clone())
+            .bearer_auth(token)
+            .query(&query_params)
+            .send()
+            .await?
+            .map_error()
+            .await?
+            .json::<EventListResponse>()
+            .await?;
+        Ok((
+            response.items.into_iter().map(Into::into).collect(),
+            response.next_page_token,
+        ))
+    }
+
+    /// Occurrences of a recurring event between `start` and `end`.
+    pub(crate) async fn list_instances(
+        &self,
+        token: &str,
+        event_id: &str,
         start: Option<UtcDateTime>,
         end: Option<UtcDateTime>,
     ) -> Result<Vec<GoogleEvent>, GoogleClientError> {
         let mut responses: Vec<GoogleEvent> = Vec::new();
         let mut next_page_token = None;
-        let now = match start {
-            Some(time) => time,
-            None => chrono::offset::Utc::now(),
-        }
-        .to_rfc3339();
         loop {
-            let mut query_params = Vec::from([
-                ("maxResults", PAGE_SIZE.to_owned()),
-                ("timeMin", now.clone()),
-            ]);
+            let mut query_params = Vec::new();
+            if let Some(time) = start {
+                query_params.push(("timeMin", time.to_rfc3339()));
+            }
             if let Some(time) = end {
                 query_params.push(("timeMax", time.to_rfc3339()));
             }
             if let Some(token) = next_page_token {
                 query_params.push(("pageToken", token));
             }
-            if let Some(ref q) = search_param {
-                query_params.push(("q", q.clone()));
-            }
             let response = self
                 .client
-                .get(self.base_url.clone())
-                .headers(self.auth_headers.clone())
+                .get(self.base_url.join(&format!("{event_id}/instances"))?)
+                .bearer_auth(token)
                 .query(&query_params)
                 .send()
-                .await
-                .map_err(Into::<GoogleClientError>::into)?
-                .map_error()?
+                .await?
+                .map_error()
+                .await?
                 .json::<EventListResponse>()
                 .await?;
 
             next_page_token = response.next_page_token.clone();
-            responses.append(&mut response.items.into_iter().map(Into::into).collect());
+            responses.extend(response.items.into_iter().map(Into::into));
             if next_page_token.is_none() {
                 break;
             }
@@ -86,60 +142,209 @@ impl GoogleEventsClient {
         Ok(responses)
     }
 
-    pub(crate) async fn get_event(&self, event_id: &str) -> Result<GoogleEvent, GoogleClientError> {
+    pub(crate) async fn get_event(
+        &self,
+        token: &str,
+        event_id: &str,
+    ) -> Result<GoogleEvent, GoogleClientError> {
         let response = self
             .client
             .get(self.base_url.join(event_id)?)
-            .headers(self.auth_headers.clone())
+            .bearer_auth(token)
             .send()
             .await?
-            .map_error()?
-            .json::<EventResponse>()
+            .map_error()
+            .await?
+            .json::<RawEventResponse>()
             .await?;
         Ok(response.into())
     }
 
     pub(crate) async fn create_event(
         &self,
+        token: &str,
         event: &EventPost,
     ) -> Result<GoogleEvent, GoogleClientError> {
         let response = self
             .client
             .post(self.base_url.clone())
-            .headers(self.auth_headers.clone())
+            .bearer_auth(token)
             .json(&event)
             .send()
             .await?
-            .map_error()?
-            .json::<EventResponse>()
+            .map_error()
+            .await?
+            .json::<RawEventResponse>()
+            .await?;
+        Ok(response.into())
+    }
+
+    /// Puts a snapshot back, undeleting the event. Events Google has already purged
+    /// are inserted again with a new id.
+    pub(crate) async fn restore_event(
+        &self,
+        token: &str,
+        snapshot: &GoogleEventSnapshot,
+    ) -> Result<GoogleEvent, GoogleClientError> {
+        if let Some(event_id) = snapshot.id() {
+            let response = self
+                .client
+                .put(self.base_url.join(event_id)?)
+                .bearer_auth(token)
+                .json(&snapshot.restore_body(true))
+                .send()
+                .await?
+                .map_error()
+                .await;
+            match response {
+                Err(GoogleClientError::NotFound { .. } | GoogleClientError::Gone { .. }) => {}
+                response => return Ok(response?.json::<RawEventResponse>().await?.into()),
+            }
+        }
+        let response = self
+            .client
+            .post(self.base_url.clone())
+            .bearer_auth(token)
+            .json(&snapshot.restore_body(false))
+            .send()
+            .await?
+            .map_error()
+            .await?
+            .json::<RawEventResponse>()
             .await?;
         Ok(response.into())
     }
 
     pub(crate) async fn update_event(
         &self,
+        token: &str,
         event_id: &str,
         event: &EventPatch,
     ) -> Result<GoogleEvent, GoogleClientError> {
         let response = self
             .client
             .patch(self.base_url.join(event_id)?)
-            .headers(self.auth_headers.clone())
+            .bearer_auth(token)
             .json(&event)
             .send()
             .await?
-            .map_error()?
-            .json::<EventResponse>()
+            .map_error()
+            .await?
+            .json::<RawEventResponse>()
             .await?;
         Ok(response.into())
     }
 
-    pub(crate) async fn delete_event(&self, event_id: &str) -> Result<Response, GoogleClientError> {
+    pub(crate) async fn delete_event(
+        &self,
+        token: &str,
+        event_id: &str,
+    ) -> Result<Response, GoogleClientError> {
         self.client
             .delete(self.base_url.join(event_id)?)
-            .headers(self.auth_headers.clone())
+            .bearer_auth(token)
             .send()
             .await?
             .map_error()
+            .await
+    }
+
+    pub(crate) async fn get_colors(&self, token: &str) -> Result<GoogleColors, GoogleClientError> {
+        let response = self
+            .client
+            .get(self.colors_url.clone())
+            .bearer_auth(token)
+            .send()
+            .await?
+            .map_error()
+            .await?
+            .json::<ColorsResponse>()
+            .await?;
+        Ok(response.into())
+    }
+
+    pub(crate) async fn query_free_busy(
+        &self,
+        token: &str,
+        query: &FreeBusyPost,
+    ) -> Result<HashMap<String, GoogleFreeBusy>, GoogleClientError> {
+        let response = self
+            .client
+            .post(self.free_busy_url.clone())
+            .bearer_auth(token)
+            .json(query)
+            .send()
+            .await?
+            .map_error()
+            .await?
+            .json::<FreeBusyResponse>()
+            .await?;
+        Ok(response
+            .calendars
+            .into_iter()
+            .map(|(id, calendar)| (id, calendar.into()))
+            .collect())
+    }
+
+    pub(crate) async fn watch_events(
+        &self,
+        token: &str,
+        watch: &WatchPost,
+    ) -> Result<GoogleChannel, GoogleClientError> {
+        let response = self
+            .client
+            .post(self.base_url.join("watch")?)
+            .bearer_auth(token)
+            .json(watch)
+            .send()
+            .await?
+            .map_error()
+            .await?
+            .json::<ChannelResponse>()
+            .await?;
+        Ok(response.into())
+    }
+
+    pub(crate) async fn stop_channel(
+        &self,
+        token: &str,
+        channel: &ChannelStopPost,
+    ) -> Result<Response, GoogleClientError> {
+        self.client
+            .post(self.channels_stop_url.clone())
+            .bearer_auth(token)
+            .json(channel)
+            .send()
+            .await?
+            .map_error()
+            .await
+    }
+
+    pub(crate) async fn batch(
+        &self,
+        token: &str,
+        requests: &[GoogleBatchRequest],
+    ) -> Result<Vec<GoogleBatchResult>, GoogleClientError> {
+        let response = self
+            .client
+            .post(self.batch_url.clone())
+            .bearer_auth(token)
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/mixed; boundary={BATCH_BOUNDARY}"),
+            )
+            .header(header::ACCEPT, "multipart/mixed")
+            .body(encode_batch(self.base_url.path(), requests)?)
+            .send()
+            .await?
+            .map_error()
+            .await?;
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+        decode_batch(&content_type, &response.text().await?, requests.len())
     }
 }
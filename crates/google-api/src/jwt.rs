@@ -62,6 +62,10 @@ impl JsonWebToken {
         encoding_key: EncodingKey::from_rsa_pem(private_key.as_bytes()).map_err(|_| JWTError::InvalidKey)?,
     })
     }
+    /// Email address of the service account.
+    pub fn issuer(&self) -> &EmailAddress {
+        &self.claims.iss
+    }
     pub fn encode(&self) -> Result<String, JWTError> {
         encode((&self.header).into(), &self.claims, &self.encoding_key)
             .map_err(|err| JWTError::EncodingError { error: err.into() })
@@ -1,123 +1,337 @@
-use async_recursion::async_recursion;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use color_eyre::eyre::eyre;
+use email_address::EmailAddress;
+use futures::{stream, Stream, TryStreamExt};
 use reqwest::Response;
 
+use api_models::{AclRulePost, FreeBusyItemPost, FreeBusyPost};
+use auth::Authenticator;
+use batch::{GoogleBatchResult, GoogleEventBatch, MAX_BATCH_SIZE};
+use calendars_client::GoogleCalendarsClient;
 use errors::GoogleClientError;
 use events_client::GoogleEventsClient;
 use jwt::JsonWebToken;
-use models::{AccessToken, GoogleEvent, GoogleEventListParams, GoogleEventPatch, GoogleEventPost};
-use oauth2_client::Oauth2Client;
+use models::{
+    AclRole, AclScope, GoogleAclRule, GoogleCalendar, GoogleCalendarPost, GoogleChannel,
+    GoogleColors, GoogleEvent, GoogleEventListParams, GoogleEventPatch, GoogleEventPost,
+    GoogleEventSnapshot, GoogleFreeBusy, GoogleWatchPost, UtcDateTime,
+};
 
 pub mod api_models;
+pub mod auth;
+pub mod batch;
+pub mod calendars_client;
 pub mod errors;
 pub mod events_client;
 pub mod jwt;
 pub mod models;
 pub mod oauth2_client;
+pub mod recurrence;
 
+#[derive(Clone)]
 pub struct GoogleClient {
     events_client: GoogleEventsClient,
-    jwt: JsonWebToken,
-    access_token: AccessToken,
+    calendars_client: GoogleCalendarsClient,
+    auth: Arc<Authenticator>,
 }
 
 impl GoogleClient {
     pub async fn new(
-        mut events_client: GoogleEventsClient,
+        events_client: GoogleEventsClient,
         jwt: JsonWebToken,
     ) -> Result<Self, GoogleClientError> {
-        let access_token = Oauth2Client::new(&jwt)?.get_token().await?;
-        events_client.update_auth_headers(&access_token.token);
         Ok(GoogleClient {
             events_client,
-            jwt,
-            access_token,
+            calendars_client: GoogleCalendarsClient::new()?,
+            auth: Arc::new(Authenticator::new(jwt).await?),
+        })
+    }
+
+    /// Email address of the service account, the creator of the events it inserts.
+    pub fn service_account_email(&self) -> &EmailAddress {
+        self.auth.email()
+    }
+
+    /// Client for the events of another calendar, sharing the access token with this one.
+    pub fn with_calendar(&self, calendar_id: &str) -> Result<Self, GoogleClientError> {
+        Ok(GoogleClient {
+            events_client: self.events_client.with_calendar(calendar_id)?,
+            ..self.clone()
         })
     }
 
-    async fn refresh_token(&mut self) -> Result<(), GoogleClientError> {
-        let jwt = self.jwt.clone().refresh();
-        let access_token = Oauth2Client::new(&jwt)?.get_token().await?;
-        self.jwt = jwt;
-        self.events_client.update_auth_headers(&access_token.token);
-        self.access_token = access_token;
-        Ok(())
+    /// Id of the calendar whose events this client works with.
+    pub fn calendar_id(&self) -> &str {
+        self.events_client.calendar_id()
     }
 
-    async fn check_token(&mut self) -> Result<(), GoogleClientError> {
-        if !self.access_token.is_valid() {
-            self.refresh_token().await?;
+    /// Runs `request` with a valid access token, retrying once with a new token if it has expired.
+    async fn with_token<'a, F, Fut, T>(&'a self, request: F) -> Result<T, GoogleClientError>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<T, GoogleClientError>> + 'a,
+    {
+        let token = self.auth.token().await?;
+        match request(token.clone()).await {
+            Err(GoogleClientError::TokenExpired) => {
+                let token = self.auth.refresh(&token).await?;
+                request(token).await
+            }
+            response => response,
         }
-        Ok(())
     }
 
-    #[async_recursion]
+    /// Streams matching events, fetching the next page once the previous one is consumed.
+    pub fn events<'a>(
+        &'a self,
+        params: &'a GoogleEventListParams,
+    ) -> impl Stream<Item = Result<GoogleEvent, GoogleClientError>> + 'a {
+        // state is the next page token, `None` once the last page has been fetched
+        stream::try_unfold(
+            Some(None),
+            move |page_token: Option<Option<String>>| async move {
+                let Some(page_token) = page_token else {
+                    return Ok::<_, GoogleClientError>(None);
+                };
+                let (events, next_page_token) = self
+                    .with_token(|token| {
+                        let page_token = page_token.clone();
+                        async move {
+                            self.events_client
+                                .list_events(&token, params, page_token.as_deref())
+                                .await
+                        }
+                    })
+                    .await?;
+                Ok(Some((
+                    stream::iter(events.into_iter().map(Ok)),
+                    next_page_token.map(Some),
+                )))
+            },
+        )
+        .try_flatten()
+    }
     pub async fn list_events(
-        &mut self,
+        &self,
         params: &GoogleEventListParams,
     ) -> Result<Vec<GoogleEvent>, GoogleClientError> {
-        self.check_token().await?;
-        let response = self
-            .events_client
-            .list_events(params.search_param.clone(), params.start, params.end)
-            .await;
-        if let Err(GoogleClientError::TokenExpired) = response {
-            self.refresh_token().await?;
-            return self.list_events(params).await;
-        } else {
-            let mut events = response?;
-            if let Some(email) = &params.creator_email {
-                events.retain(|x| x.creator_email.eq(email));
-            }
-            return Ok(events);
-        }
+        self.events(params).try_collect().await
     }
-    pub async fn get_event(&mut self, event_id: &str) -> Result<GoogleEvent, GoogleClientError> {
-        self.check_token().await?;
-        let response = self.events_client.get_event(event_id).await;
-        if let Err(GoogleClientError::TokenExpired) = response {
-            self.refresh_token().await?;
-            return self.events_client.get_event(event_id).await;
-        }
-        response
+    /// Occurrences of a recurring event between `start` and `end`. An occurrence is changed
+    /// or cancelled as an exception by updating or deleting it by its own id.
+    pub async fn list_instances(
+        &self,
+        event_id: &str,
+        start: Option<UtcDateTime>,
+        end: Option<UtcDateTime>,
+    ) -> Result<Vec<GoogleEvent>, GoogleClientError> {
+        self.with_token(|token| async move {
+            self.events_client
+                .list_instances(&token, event_id, start, end)
+                .await
+        })
+        .await
+    }
+    pub async fn get_event(&self, event_id: &str) -> Result<GoogleEvent, GoogleClientError> {
+        self.with_token(|token| async move { self.events_client.get_event(&token, event_id).await })
+            .await
     }
     pub async fn create_event(
-        &mut self,
+        &self,
         event: &GoogleEventPost,
     ) -> Result<GoogleEvent, GoogleClientError> {
-        self.check_token().await?;
-        let response = self.events_client.create_event(&event.into()).await;
-        if let Err(GoogleClientError::TokenExpired) = response {
-            self.refresh_token().await?;
-            return self.events_client.create_event(&event.into()).await;
-        }
-        response
+        let event = event.into();
+        self.with_token(|token| {
+            let event = &event;
+            async move { self.events_client.create_event(&token, event).await }
+        })
+        .await
     }
     pub async fn update_event(
-        &mut self,
+        &self,
         event_id: &str,
         event: &GoogleEventPatch,
     ) -> Result<GoogleEvent, GoogleClientError> {
-        self.check_token().await?;
-        let response = self
-            .events_client
-            .update_event(event_id, &event.into())
-            .await;
-        if let Err(GoogleClientError::TokenExpired) = response {
-            self.refresh_token().await?;
-            return self
-                .events_client
-                .update_event(event_id, &event.into())
-                .await;
-        }
-        response
-    }
-    pub async fn delete_event(&mut self, event_id: &str) -> Result<Response, GoogleClientError> {
-        self.check_token().await?;
-        let response = self.events_client.delete_event(event_id).await;
-        if let Err(GoogleClientError::TokenExpired) = response {
-            self.refresh_token().await?;
-            return self.events_client.delete_event(event_id).await;
+        let event = event.into();
+        self.with_token(|token| {
+            let event = &event;
+            async move {
+                self.events_client
+                    .update_event(&token, event_id, event)
+                    .await
+            }
+        })
+        .await
+    }
+    pub async fn restore_event(
+        &self,
+        snapshot: &GoogleEventSnapshot,
+    ) -> Result<GoogleEvent, GoogleClientError> {
+        self.with_token(
+            |token| async move { self.events_client.restore_event(&token, snapshot).await },
+        )
+        .await
+    }
+    pub async fn delete_event(&self, event_id: &str) -> Result<Response, GoogleClientError> {
+        self.with_token(
+            |token| async move { self.events_client.delete_event(&token, event_id).await },
+        )
+        .await
+    }
+
+    pub async fn get_colors(&self) -> Result<GoogleColors, GoogleClientError> {
+        self.with_token(|token| async move { self.events_client.get_colors(&token).await })
+            .await
+    }
+    /// Returns busy time between `start` and `end` for each of the calendars.
+    pub async fn query_free_busy(
+        &self,
+        start: UtcDateTime,
+        end: UtcDateTime,
+        calendar_ids: &[String],
+    ) -> Result<HashMap<String, GoogleFreeBusy>, GoogleClientError> {
+        let query = FreeBusyPost {
+            time_min: start,
+            time_max: end,
+            items: calendar_ids
+                .iter()
+                .map(|id| FreeBusyItemPost { id: id.clone() })
+                .collect(),
+        };
+        self.with_token(|token| {
+            let query = &query;
+            async move { self.events_client.query_free_busy(&token, query).await }
+        })
+        .await
+    }
+    /// Starts push notifications about changes to the calendar's events.
+    pub async fn watch_events(
+        &self,
+        watch: &GoogleWatchPost,
+    ) -> Result<GoogleChannel, GoogleClientError> {
+        let watch = watch.into();
+        self.with_token(|token| {
+            let watch = &watch;
+            async move { self.events_client.watch_events(&token, watch).await }
+        })
+        .await
+    }
+    pub async fn stop_channel(
+        &self,
+        channel: &GoogleChannel,
+    ) -> Result<Response, GoogleClientError> {
+        let channel = channel.into();
+        self.with_token(|token| {
+            let channel = &channel;
+            async move { self.events_client.stop_channel(&token, channel).await }
+        })
+        .await
+    }
+    /// Executes the batch in chunks of `MAX_BATCH_SIZE`, returning one result per request in order.
+    /// Chunks are applied independently: a failed chunk fails each of its requests
+    /// while the results of the other chunks are kept.
+    pub async fn execute_batch(&self, batch: &GoogleEventBatch) -> Vec<GoogleBatchResult> {
+        let mut results = Vec::with_capacity(batch.len());
+        for requests in batch.requests.chunks(MAX_BATCH_SIZE) {
+            match self
+                .with_token(|token| async move { self.events_client.batch(&token, requests).await })
+                .await
+            {
+                Ok(chunk) => results.extend(chunk),
+                Err(e) => results.extend(requests.iter().map(|_| {
+                    Err(GoogleClientError::Other {
+                        error: eyre!("Batch request failed: {e}"),
+                    })
+                })),
+            }
         }
-        response
+        results
+    }
+
+    pub async fn list_calendars(&self) -> Result<Vec<GoogleCalendar>, GoogleClientError> {
+        self.with_token(|token| async move { self.calendars_client.list_calendars(&token).await })
+            .await
+    }
+    pub async fn get_calendar(
+        &self,
+        calendar_id: &str,
+    ) -> Result<GoogleCalendar, GoogleClientError> {
+        self.with_token(|token| async move {
+            self.calendars_client
+                .get_calendar(&token, calendar_id)
+                .await
+        })
+        .await
+    }
+    /// Creates a secondary calendar owned by the authenticated account.
+    pub async fn create_calendar(
+        &self,
+        calendar: &GoogleCalendarPost,
+    ) -> Result<GoogleCalendar, GoogleClientError> {
+        let calendar = calendar.into();
+        self.with_token(|token| {
+            let calendar = &calendar;
+            async move {
+                self.calendars_client
+                    .create_calendar(&token, calendar)
+                    .await
+            }
+        })
+        .await
+    }
+    pub async fn delete_calendar(&self, calendar_id: &str) -> Result<Response, GoogleClientError> {
+        self.with_token(|token| async move {
+            self.calendars_client
+                .delete_calendar(&token, calendar_id)
+                .await
+        })
+        .await
+    }
+
+    pub async fn list_acl(
+        &self,
+        calendar_id: &str,
+    ) -> Result<Vec<GoogleAclRule>, GoogleClientError> {
+        self.with_token(
+            |token| async move { self.calendars_client.list_acl(&token, calendar_id).await },
+        )
+        .await
+    }
+    /// Grants `role` on the calendar to `scope`, emailing the grantee if `send_notifications` is set.
+    pub async fn insert_acl(
+        &self,
+        calendar_id: &str,
+        role: AclRole,
+        scope: &AclScope,
+        send_notifications: bool,
+    ) -> Result<GoogleAclRule, GoogleClientError> {
+        let rule = AclRulePost {
+            role,
+            scope: scope.into(),
+        };
+        self.with_token(|token| {
+            let rule = &rule;
+            async move {
+                self.calendars_client
+                    .insert_acl(&token, calendar_id, rule, send_notifications)
+                    .await
+            }
+        })
+        .await
+    }
+    pub async fn delete_acl(
+        &self,
+        calendar_id: &str,
+        rule_id: &str,
+    ) -> Result<Response, GoogleClientError> {
+        self.with_token(|token| async move {
+            self.calendars_client
+                .delete_acl(&token, calendar_id, rule_id)
+                .await
+        })
+        .await
     }
 }
@@ -1,9 +1,23 @@
-use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use color_eyre::eyre::eyre;
 use email_address::EmailAddress;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    api_models::{EventPatch, EventPost, EventResponse, TimePost},
+    api_models::{
+        AclRuleResponse, AclScopeBody, BusyResponse, CalendarListEntryResponse, CalendarPost,
+        CalendarResponse, ChannelResponse, ChannelStopPost, ColorResponse, ColorsResponse,
+        EventPatch, EventPost, ExtendedProperties, FreeBusyCalendarResponse, RawEventResponse,
+        ReminderOverride, Reminders, SourcePost, TimePost, TimeResponse, WatchParams, WatchPost,
+    },
+    errors::GoogleClientError,
     oauth2_client::TokenResponse,
+    recurrence::GoogleRecurrence,
 };
 
 pub type UtcDateTime = DateTime<Utc>;
@@ -36,20 +50,233 @@ pub struct GoogleEvent {
     pub id: String,
     pub summary: Option<String>,
     pub description: Option<String>,
-    pub start: Option<UtcDateTime>,
-    pub end: Option<UtcDateTime>,
-    pub creator_email: EmailAddress,
+    pub location: Option<String>,
+    pub source_url: Option<String>,
+    pub start: Option<GoogleEventTime>,
+    pub end: Option<GoogleEventTime>,
+    pub creator_email: Option<EmailAddress>,
+    /// Deleted events are only listed with `show_deleted`.
+    pub cancelled: bool,
+    /// Last modification, for cancelled events the time of deletion.
+    pub updated: Option<UtcDateTime>,
+    pub private_properties: HashMap<String, String>,
+    pub color_id: Option<String>,
+    /// `None` if the event uses the calendar's default reminders.
+    pub reminders: Option<Vec<GoogleReminder>>,
+    /// Set for the first event of a recurring series.
+    pub recurrence: Option<GoogleRecurrence>,
+    /// Series id if the event is an occurrence of a recurring event.
+    pub recurring_event_id: Option<String>,
+    /// Start of the occurrence as defined by the series, before it was moved.
+    pub original_start: Option<GoogleEventTime>,
+    pub snapshot: GoogleEventSnapshot,
+}
+
+/// Event exactly as returned by Google, enough to restore it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GoogleEventSnapshot(serde_json::Value);
+
+/// Snapshots of events stored elsewhere, e.g. on a CalDAV server, need an `id` field.
+impl From<serde_json::Value> for GoogleEventSnapshot {
+    fn from(value: serde_json::Value) -> Self {
+        GoogleEventSnapshot(value)
+    }
+}
+
+impl GoogleEventSnapshot {
+    pub fn id(&self) -> Option<&str> {
+        self.0.get("id").and_then(serde_json::Value::as_str)
+    }
+
+    pub fn json(&self) -> &serde_json::Value {
+        &self.0
+    }
+
+    /// Body restoring the event, `with_id` is false for inserting it as a new event.
+    pub(crate) fn restore_body(&self, with_id: bool) -> serde_json::Value {
+        let mut body = self.0.clone();
+        if let Some(object) = body.as_object_mut() {
+            object.insert("status".to_owned(), "confirmed".into());
+            if !with_id {
+                object.remove("id");
+                object.remove("iCalUID");
+            }
+        }
+        body
+    }
+}
+
+/// Start or end of an event: a point in time, or a date for all-day events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoogleEventTime {
+    DateTime {
+        date_time: UtcDateTime,
+        /// Time zone the event is shown in, `None` for the calendar's time zone.
+        time_zone: Option<Tz>,
+    },
+    /// All-day event, the end date is exclusive.
+    Date(NaiveDate),
+}
+
+impl GoogleEventTime {
+    /// Point in time, `None` for all-day events.
+    pub fn date_time(&self) -> Option<UtcDateTime> {
+        match self {
+            GoogleEventTime::DateTime { date_time, .. } => Some(*date_time),
+            GoogleEventTime::Date(_) => None,
+        }
+    }
+
+    pub fn is_all_day(&self) -> bool {
+        matches!(self, GoogleEventTime::Date(_))
+    }
+
+    /// Unknown time zones are dropped, `None` if the response has neither a date nor a time.
+    fn from_response(value: TimeResponse) -> Option<Self> {
+        match (value.date_time, value.date) {
+            (Some(date_time), _) => Some(GoogleEventTime::DateTime {
+                date_time,
+                time_zone: value.time_zone.and_then(|x| x.parse().ok()),
+            }),
+            (None, Some(date)) => Some(GoogleEventTime::Date(date)),
+            (None, None) => None,
+        }
+    }
+}
+
+impl Display for GoogleEventTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GoogleEventTime::DateTime {
+                date_time,
+                time_zone: Some(time_zone),
+            } => write!(f, "{}", date_time.with_timezone(time_zone)),
+            GoogleEventTime::DateTime { date_time, .. } => write!(f, "{date_time}"),
+            GoogleEventTime::Date(date) => write!(f, "{date}"),
+        }
+    }
+}
+
+impl From<&GoogleEventTime> for TimePost {
+    fn from(value: &GoogleEventTime) -> Self {
+        match value {
+            GoogleEventTime::DateTime {
+                date_time,
+                time_zone,
+            } => TimePost {
+                date: None,
+                date_time: Some(*date_time),
+                time_zone: time_zone.map(|x| x.name().to_owned()),
+            },
+            GoogleEventTime::Date(date) => TimePost {
+                date: Some(*date),
+                date_time: None,
+                time_zone: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub struct GoogleReminder {
+    pub method: ReminderMethod,
+    pub minutes: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReminderMethod {
+    Email,
+    Popup,
+}
+
+impl From<ReminderOverride> for GoogleReminder {
+    fn from(value: ReminderOverride) -> Self {
+        GoogleReminder {
+            method: value.method,
+            minutes: value.minutes,
+        }
+    }
+}
+
+impl From<&GoogleReminder> for ReminderOverride {
+    fn from(value: &GoogleReminder) -> Self {
+        ReminderOverride {
+            method: value.method,
+            minutes: value.minutes,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GoogleColor {
+    pub background: String,
+    pub foreground: String,
+}
+
+/// Color palettes available for calendars and events, keyed by color id.
+#[derive(Debug, Clone)]
+pub struct GoogleColors {
+    pub calendar: HashMap<String, GoogleColor>,
+    pub event: HashMap<String, GoogleColor>,
 }
 
-impl From<EventResponse> for GoogleEvent {
-    fn from(value: EventResponse) -> Self {
+impl From<ColorResponse> for GoogleColor {
+    fn from(value: ColorResponse) -> Self {
+        GoogleColor {
+            background: value.background,
+            foreground: value.foreground,
+        }
+    }
+}
+
+impl From<ColorsResponse> for GoogleColors {
+    fn from(value: ColorsResponse) -> Self {
+        GoogleColors {
+            calendar: value
+                .calendar
+                .into_iter()
+                .map(|(id, color)| (id, color.into()))
+                .collect(),
+            event: value
+                .event
+                .into_iter()
+                .map(|(id, color)| (id, color.into()))
+                .collect(),
+        }
+    }
+}
+
+impl From<RawEventResponse> for GoogleEvent {
+    fn from(raw: RawEventResponse) -> Self {
+        let value = raw.event;
         GoogleEvent {
             id: value.id,
             summary: value.summary,
             description: value.description,
-            start: value.start.date_time,
-            end: value.end.date_time,
-            creator_email: value.creator.email,
+            location: value.location,
+            source_url: value.source.map(|x| x.url),
+            start: GoogleEventTime::from_response(value.start),
+            end: GoogleEventTime::from_response(value.end),
+            creator_email: value.creator.map(|x| x.email),
+            cancelled: value.status.as_deref() == Some("cancelled"),
+            updated: value.updated,
+            private_properties: value
+                .extended_properties
+                .map(|x| x.private)
+                .unwrap_or_default(),
+            color_id: value.color_id,
+            reminders: value
+                .reminders
+                .filter(|x| !x.use_default)
+                .map(|x| x.overrides.into_iter().map(Into::into).collect()),
+            recurrence: value.recurrence.map(|x| GoogleRecurrence::from_lines(&x)),
+            recurring_event_id: value.recurring_event_id,
+            original_start: value
+                .original_start_time
+                .and_then(GoogleEventTime::from_response),
+            snapshot: GoogleEventSnapshot(raw.json),
         }
     }
 }
@@ -59,24 +286,426 @@ impl GoogleEvent {
         self.summary.clone().unwrap_or("\"\"".to_owned())
     }
     pub fn start(&self) -> String {
-        self.start.map(|s| s.to_string()).unwrap_or("-".to_owned())
+        self.start.map_or("-".to_owned(), |s| s.to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GoogleBusyPeriod {
+    pub start: UtcDateTime,
+    pub end: UtcDateTime,
+}
+
+/// Busy time of one calendar, `errors` holds reasons if it could not be queried.
+#[derive(Debug, Clone)]
+pub struct GoogleFreeBusy {
+    pub busy: Vec<GoogleBusyPeriod>,
+    pub errors: Vec<String>,
+}
+
+impl From<BusyResponse> for GoogleBusyPeriod {
+    fn from(value: BusyResponse) -> Self {
+        GoogleBusyPeriod {
+            start: value.start,
+            end: value.end,
+        }
+    }
+}
+
+impl From<FreeBusyCalendarResponse> for GoogleFreeBusy {
+    fn from(value: FreeBusyCalendarResponse) -> Self {
+        GoogleFreeBusy {
+            busy: value.busy.into_iter().map(Into::into).collect(),
+            errors: value.errors.into_iter().map(|x| x.reason).collect(),
+        }
+    }
+}
+
+/// Calendar from the authenticated account's calendar list.
+#[derive(Debug, Clone)]
+pub struct GoogleCalendar {
+    pub id: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub time_zone: Option<String>,
+    /// Access the account has to the calendar, `None` for calendars fetched by id.
+    pub access_role: Option<AclRole>,
+    pub primary: bool,
+}
+
+impl From<CalendarListEntryResponse> for GoogleCalendar {
+    fn from(value: CalendarListEntryResponse) -> Self {
+        GoogleCalendar {
+            id: value.id,
+            summary: value.summary,
+            description: value.description,
+            time_zone: value.time_zone,
+            access_role: value.access_role,
+            primary: value.primary,
+        }
+    }
+}
+
+impl From<CalendarResponse> for GoogleCalendar {
+    fn from(value: CalendarResponse) -> Self {
+        GoogleCalendar {
+            id: value.id,
+            summary: value.summary,
+            description: value.description,
+            time_zone: value.time_zone,
+            access_role: None,
+            primary: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GoogleCalendarPost {
+    pub summary: String,
+    pub description: Option<String>,
+    /// IANA time zone name, e.g. `Europe/Moscow`.
+    pub time_zone: Option<String>,
+}
+
+impl From<&GoogleCalendarPost> for CalendarPost {
+    fn from(value: &GoogleCalendarPost) -> Self {
+        CalendarPost {
+            summary: value.summary.clone(),
+            description: value.description.clone(),
+            time_zone: value.time_zone.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AclRole {
+    None,
+    FreeBusyReader,
+    Reader,
+    Writer,
+    Owner,
+}
+
+/// Who an ACL rule grants access to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AclScope {
+    /// Public access.
+    Default,
+    User(EmailAddress),
+    Group(EmailAddress),
+    Domain(String),
+}
+
+impl From<&AclScope> for AclScopeBody {
+    fn from(value: &AclScope) -> Self {
+        let (scope_type, value) = match value {
+            AclScope::Default => ("default", None),
+            AclScope::User(email) => ("user", Some(email.to_string())),
+            AclScope::Group(email) => ("group", Some(email.to_string())),
+            AclScope::Domain(domain) => ("domain", Some(domain.clone())),
+        };
+        AclScopeBody {
+            scope_type: scope_type.to_owned(),
+            value,
+        }
+    }
+}
+
+impl TryFrom<AclScopeBody> for AclScope {
+    type Error = GoogleClientError;
+
+    fn try_from(value: AclScopeBody) -> Result<Self, Self::Error> {
+        let email = |value: Option<String>| {
+            value
+                .unwrap_or_default()
+                .parse::<EmailAddress>()
+                .map_err(|err| GoogleClientError::Other { error: err.into() })
+        };
+        match value.scope_type.as_str() {
+            "default" => Ok(AclScope::Default),
+            "user" => Ok(AclScope::User(email(value.value)?)),
+            "group" => Ok(AclScope::Group(email(value.value)?)),
+            "domain" => Ok(AclScope::Domain(value.value.unwrap_or_default())),
+            other => Err(GoogleClientError::Other {
+                error: eyre!("Unknown ACL scope type: {other}"),
+            }),
+        }
     }
 }
 
 #[derive(Debug, Clone)]
+pub struct GoogleAclRule {
+    pub id: String,
+    pub role: AclRole,
+    pub scope: AclScope,
+}
+
+impl TryFrom<AclRuleResponse> for GoogleAclRule {
+    type Error = GoogleClientError;
+
+    fn try_from(value: AclRuleResponse) -> Result<Self, Self::Error> {
+        Ok(GoogleAclRule {
+            id: value.id,
+            role: value.role,
+            scope: value.scope.try_into()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoogleEventOrder {
+    /// Only allowed for single events.
+    StartTime,
+    Updated,
+}
+
+/// Query for listing events, built with chained calls, e.g.
+/// `GoogleEventListParams::new().start(now).private_property("key", "value")`.
+#[derive(Debug, Clone, Default)]
 pub struct GoogleEventListParams {
-    pub search_param: Option<String>,
-    pub start: Option<UtcDateTime>,
-    pub end: Option<UtcDateTime>,
-    pub creator_email: Option<EmailAddress>,
+    search_param: Option<String>,
+    start: Option<UtcDateTime>,
+    end: Option<UtcDateTime>,
+    single_events: bool,
+    order_by: Option<GoogleEventOrder>,
+    show_deleted: bool,
+    updated_min: Option<UtcDateTime>,
+    private_properties: Vec<(String, String)>,
+    fields: Option<String>,
+    page_size: Option<u16>,
+}
+
+impl GoogleEventListParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Free text search in summary, description, location and attendees.
+    #[must_use]
+    pub fn search(mut self, query: &str) -> Self {
+        self.search_param = Some(query.to_owned());
+        self
+    }
+    /// Lower bound for the event end time.
+    #[must_use]
+    pub fn start(mut self, start: UtcDateTime) -> Self {
+        self.start = Some(start);
+        self
+    }
+    /// Upper bound for the event start time.
+    #[must_use]
+    pub fn end(mut self, end: UtcDateTime) -> Self {
+        self.end = Some(end);
+        self
+    }
+    /// Expands recurring events into their occurrences.
+    #[must_use]
+    pub fn single_events(mut self, single_events: bool) -> Self {
+        self.single_events = single_events;
+        self
+    }
+    #[must_use]
+    pub fn order_by(mut self, order: GoogleEventOrder) -> Self {
+        self.order_by = Some(order);
+        self
+    }
+    /// Includes cancelled events and occurrences.
+    #[must_use]
+    pub fn show_deleted(mut self, show_deleted: bool) -> Self {
+        self.show_deleted = show_deleted;
+        self
+    }
+    /// Only events modified after `updated_min`.
+    #[must_use]
+    pub fn updated_min(mut self, updated_min: UtcDateTime) -> Self {
+        self.updated_min = Some(updated_min);
+        self
+    }
+    /// Only events having the private extended property, repeated calls must all match.
+    #[must_use]
+    pub fn private_property(mut self, key: &str, value: &str) -> Self {
+        self.private_properties
+            .push((key.to_owned(), value.to_owned()));
+        self
+    }
+    /// Partial response selector, e.g. `items(id,summary),nextPageToken`.
+    #[must_use]
+    pub fn fields(mut self, fields: &str) -> Self {
+        self.fields = Some(fields.to_owned());
+        self
+    }
+    /// Events per page, Google's default is 250.
+    #[must_use]
+    pub fn page_size(mut self, page_size: u16) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    pub(crate) fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+        if let Some(q) = &self.search_param {
+            query.push(("q", q.clone()));
+        }
+        if let Some(time) = self.start {
+            query.push(("timeMin", time.to_rfc3339()));
+        }
+        if let Some(time) = self.end {
+            query.push(("timeMax", time.to_rfc3339()));
+        }
+        if self.single_events {
+            query.push(("singleEvents", "true".to_owned()));
+        }
+        if let Some(order) = self.order_by {
+            let order = match order {
+                GoogleEventOrder::StartTime => "startTime",
+                GoogleEventOrder::Updated => "updated",
+            };
+            query.push(("orderBy", order.to_owned()));
+        }
+        if self.show_deleted {
+            query.push(("showDeleted", "true".to_owned()));
+        }
+        if let Some(time) = self.updated_min {
+            query.push(("updatedMin", time.to_rfc3339()));
+        }
+        for (key, value) in &self.private_properties {
+            query.push(("privateExtendedProperty", format!("{key}={value}")));
+        }
+        if let Some(fields) = &self.fields {
+            query.push(("fields", fields.clone()));
+        }
+        if let Some(page_size) = self.page_size {
+            query.push(("maxResults", page_size.to_string()));
+        }
+        query
+    }
+}
+
+/// Push notification channel watching the events of a calendar.
+#[derive(Debug, Clone)]
+pub struct GoogleChannel {
+    pub id: String,
+    pub resource_id: String,
+    pub token: Option<String>,
+    pub expiration: Option<UtcDateTime>,
+}
+
+impl From<ChannelResponse> for GoogleChannel {
+    fn from(value: ChannelResponse) -> Self {
+        GoogleChannel {
+            id: value.id,
+            resource_id: value.resource_id,
+            token: value.token,
+            expiration: value
+                .expiration
+                .and_then(|x| x.parse::<i64>().ok())
+                .and_then(DateTime::from_timestamp_millis),
+        }
+    }
+}
+
+impl From<&GoogleChannel> for ChannelStopPost {
+    fn from(value: &GoogleChannel) -> Self {
+        ChannelStopPost {
+            id: value.id.clone(),
+            resource_id: value.resource_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GoogleWatchPost {
+    /// Unique channel id chosen by the caller.
+    pub id: String,
+    /// HTTPS address notifications are posted to.
+    pub address: String,
+    /// Sent back with every notification to verify its origin.
+    pub token: Option<String>,
+    /// Requested channel lifetime, Google caps it.
+    pub ttl: Option<Duration>,
+}
+
+impl From<&GoogleWatchPost> for WatchPost {
+    fn from(value: &GoogleWatchPost) -> Self {
+        WatchPost {
+            id: value.id.clone(),
+            channel_type: "web_hook".to_owned(),
+            address: value.address.clone(),
+            token: value.token.clone(),
+            params: value.ttl.map(|ttl| WatchParams {
+                ttl: ttl.num_seconds().to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoogleResourceState {
+    /// First message after a channel is created.
+    Sync,
+    Exists,
+    NotExists,
+}
+
+/// Push notification sent by Google to a channel address, all data is in `X-Goog-*` headers.
+#[derive(Debug, Clone)]
+pub struct GoogleNotification {
+    pub channel_id: String,
+    pub token: Option<String>,
+    pub resource_id: String,
+    pub state: GoogleResourceState,
+    pub message_number: u64,
+}
+
+impl GoogleNotification {
+    /// `None` if the request is not a channel notification.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name).and_then(|x| x.to_str().ok());
+        Some(GoogleNotification {
+            channel_id: header("X-Goog-Channel-ID")?.to_owned(),
+            token: header("X-Goog-Channel-Token").map(ToOwned::to_owned),
+            resource_id: header("X-Goog-Resource-ID")?.to_owned(),
+            state: match header("X-Goog-Resource-State")? {
+                "sync" => GoogleResourceState::Sync,
+                "exists" => GoogleResourceState::Exists,
+                "not_exists" => GoogleResourceState::NotExists,
+                _ => return None,
+            },
+            message_number: header("X-Goog-Message-Number")?.parse().ok()?,
+        })
+    }
+}
+
+/// Link to the page the event was created from, shown by Google Calendar next to the event.
+#[derive(Debug, Clone)]
+pub struct GoogleEventSource {
+    pub title: String,
+    pub url: String,
+}
+
+impl From<&GoogleEventSource> for SourcePost {
+    fn from(value: &GoogleEventSource) -> Self {
+        SourcePost {
+            title: value.title.clone(),
+            url: value.url.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct GoogleEventPost {
     pub summary: String,
     pub description: Option<String>,
-    pub start: UtcDateTime,
-    pub end: UtcDateTime,
+    pub location: Option<String>,
+    pub source: Option<GoogleEventSource>,
+    pub start: GoogleEventTime,
+    pub end: GoogleEventTime,
+    pub private_properties: HashMap<String, String>,
+    pub color_id: Option<String>,
+    /// Reminder overrides, `None` to use the calendar's default reminders.
+    pub reminders: Option<Vec<GoogleReminder>>,
+    /// Makes the event a recurring series.
+    pub recurrence: Option<GoogleRecurrence>,
 }
 
 impl From<&GoogleEventPost> for EventPost {
@@ -84,22 +713,50 @@ impl From<&GoogleEventPost> for EventPost {
         EventPost {
             summary: value.summary.clone(),
             description: value.description.clone(),
-            start: TimePost {
-                date_time: value.start,
-            },
-            end: TimePost {
-                date_time: value.end,
-            },
+            location: value.location.clone(),
+            source: value.source.as_ref().map(Into::into),
+            start: (&value.start).into(),
+            end: (&value.end).into(),
+            extended_properties: private_properties(&value.private_properties),
+            color_id: value.color_id.clone(),
+            reminders: value.reminders.as_deref().map(reminders),
+            recurrence: value.recurrence.as_ref().map(GoogleRecurrence::to_lines),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct GoogleEventPatch {
     pub summary: Option<String>,
     pub description: Option<String>,
-    pub start: Option<UtcDateTime>,
-    pub end: Option<UtcDateTime>,
+    pub location: Option<String>,
+    pub source: Option<GoogleEventSource>,
+    pub start: Option<GoogleEventTime>,
+    pub end: Option<GoogleEventTime>,
+    pub private_properties: Option<HashMap<String, String>>,
+    pub color_id: Option<String>,
+    pub reminders: Option<Vec<GoogleReminder>>,
+    pub recurrence: Option<GoogleRecurrence>,
+    /// `Some(true)` shows the event as free time, `Some(false)` as busy.
+    pub transparent: Option<bool>,
+}
+
+impl From<&GoogleEventPost> for GoogleEventPatch {
+    fn from(value: &GoogleEventPost) -> Self {
+        GoogleEventPatch {
+            summary: Some(value.summary.clone()),
+            description: value.description.clone(),
+            location: value.location.clone(),
+            source: value.source.clone(),
+            start: Some(value.start),
+            end: Some(value.end),
+            private_properties: Some(value.private_properties.clone()),
+            color_id: value.color_id.clone(),
+            reminders: value.reminders.clone(),
+            recurrence: value.recurrence.clone(),
+            transparent: None,
+        }
+    }
 }
 
 impl From<&GoogleEventPatch> for EventPatch {
@@ -107,8 +764,34 @@ impl From<&GoogleEventPatch> for EventPatch {
         EventPatch {
             summary: value.summary.clone(),
             description: value.description.clone(),
-            start: value.start.map(|date_time| TimePost { date_time }),
-            end: value.end.map(|date_time| TimePost { date_time }),
+            location: value.location.clone(),
+            source: value.source.as_ref().map(Into::into),
+            start: value.start.as_ref().map(Into::into),
+            end: value.end.as_ref().map(Into::into),
+            extended_properties: value
+                .private_properties
+                .as_ref()
+                .and_then(private_properties),
+            color_id: value.color_id.clone(),
+            reminders: value.reminders.as_deref().map(reminders),
+            recurrence: value.recurrence.as_ref().map(GoogleRecurrence::to_lines),
+            transparency: value
+                .transparent
+                .map(|x| if x { "transparent" } else { "opaque" }.to_owned()),
         }
     }
 }
+
+fn private_properties(properties: &HashMap<String, String>) -> Option<ExtendedProperties> {
+    (!properties.is_empty()).then(|| ExtendedProperties {
+        private: properties.clone(),
+        shared: HashMap::new(),
+    })
+}
+
+fn reminders(overrides: &[GoogleReminder]) -> Reminders {
+    Reminders {
+        use_default: false,
+        overrides: overrides.iter().map(Into::into).collect(),
+    }
+}
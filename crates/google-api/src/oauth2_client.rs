@@ -53,7 +53,8 @@ impl Oauth2Client {
             .form(&self.form_params)
             .send()
             .await?
-            .map_error()?
+            .map_error()
+            .await?
             .json::<TokenResponse>()
             .await?;
         Ok(response.into())
@@ -0,0 +1,192 @@
+use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::models::GoogleEventTime;
+
+const DATE_FORMAT: &str = "%Y%m%d";
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Recurrence of an event series, stored by Google as RFC 5545 `RRULE`, `RDATE` and `EXDATE` lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GoogleRecurrence {
+    /// Rule values without the `RRULE:` prefix, e.g. `FREQ=WEEKLY;BYDAY=MO`.
+    pub rules: Vec<String>,
+    /// Extra occurrences outside of the rules.
+    pub dates: Vec<GoogleEventTime>,
+    /// Occurrences removed from the series.
+    pub exceptions: Vec<GoogleEventTime>,
+}
+
+impl GoogleRecurrence {
+    /// Weekly series on the given days, e.g. a standing class.
+    pub fn weekly(weekdays: &[Weekday]) -> Self {
+        let days = weekdays.iter().map(|x| by_day(*x)).collect::<Vec<_>>();
+        GoogleRecurrence {
+            rules: vec![format!("FREQ=WEEKLY;BYDAY={}", days.join(","))],
+            ..Default::default()
+        }
+    }
+
+    #[must_use]
+    pub fn except(mut self, occurrence: GoogleEventTime) -> Self {
+        self.exceptions.push(occurrence);
+        self
+    }
+
+    pub(crate) fn to_lines(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .map(|x| format!("RRULE:{x}"))
+            .chain(self.dates.iter().map(|x| format_time("RDATE", x)))
+            .chain(self.exceptions.iter().map(|x| format_time("EXDATE", x)))
+            .collect()
+    }
+
+    /// Parses recurrence lines, skipping the ones Google does not support.
+    pub(crate) fn from_lines(lines: &[String]) -> Self {
+        let mut recurrence = GoogleRecurrence::default();
+        for line in lines {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let mut params = name.split(';');
+            match params.next() {
+                Some("RRULE") => recurrence.rules.push(value.to_owned()),
+                Some("RDATE") => recurrence.dates.extend(parse_times(params, value)),
+                Some("EXDATE") => recurrence.exceptions.extend(parse_times(params, value)),
+                _ => {}
+            }
+        }
+        recurrence
+    }
+}
+
+fn by_day(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn format_time(name: &str, time: &GoogleEventTime) -> String {
+    match time {
+        GoogleEventTime::DateTime {
+            date_time,
+            time_zone: Some(time_zone),
+        } => format!(
+            "{name};TZID={}:{}",
+            time_zone.name(),
+            date_time.with_timezone(time_zone).format(DATE_TIME_FORMAT)
+        ),
+        GoogleEventTime::DateTime { date_time, .. } => {
+            format!("{name}:{}Z", date_time.format(DATE_TIME_FORMAT))
+        }
+        GoogleEventTime::Date(date) => format!("{name};VALUE=DATE:{}", date.format(DATE_FORMAT)),
+    }
+}
+
+/// Parses a comma separated list of `RDATE`/`EXDATE` values, times without a zone are UTC.
+fn parse_times<'a>(params: impl Iterator<Item = &'a str>, value: &str) -> Vec<GoogleEventTime> {
+    let mut time_zone: Option<Tz> = None;
+    let mut is_date = false;
+    for param in params {
+        match param.split_once('=') {
+            Some(("TZID", tz)) => time_zone = tz.parse().ok(),
+            Some(("VALUE", "DATE")) => is_date = true,
+            _ => {}
+        }
+    }
+    value
+        .split(',')
+        .filter_map(|x| {
+            if is_date {
+                return NaiveDate::parse_from_str(x, DATE_FORMAT)
+                    .ok()
+                    .map(GoogleEventTime::Date);
+            }
+            let local =
+                NaiveDateTime::parse_from_str(x.trim_end_matches('Z'), DATE_TIME_FORMAT).ok()?;
+            let date_time = match time_zone {
+                Some(tz) => tz
+                    .from_local_datetime(&local)
+                    .earliest()?
+                    .with_timezone(&Utc),
+                None => Utc.from_utc_datetime(&local),
+            };
+            Some(GoogleEventTime::DateTime {
+                date_time,
+                time_zone,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moscow(text: &str) -> GoogleEventTime {
+        let local = NaiveDateTime::parse_from_str(text, DATE_TIME_FORMAT).unwrap();
+        let tz = chrono_tz::Europe::Moscow;
+        GoogleEventTime::DateTime {
+            date_time: tz.from_local_datetime(&local).unwrap().with_timezone(&Utc),
+            time_zone: Some(tz),
+        }
+    }
+
+    #[test]
+    fn writes_weekly_rules_and_exceptions() {
+        let recurrence = GoogleRecurrence::weekly(&[Weekday::Mon, Weekday::Thu])
+            .except(moscow("20250106T190000"));
+        assert_eq!(
+            recurrence.to_lines(),
+            vec![
+                "RRULE:FREQ=WEEKLY;BYDAY=MO,TH",
+                "EXDATE;TZID=Europe/Moscow:20250106T190000",
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips_lines() {
+        let recurrence = GoogleRecurrence {
+            rules: vec!["FREQ=WEEKLY;BYDAY=SA;COUNT=4".to_owned()],
+            dates: vec![GoogleEventTime::Date(
+                NaiveDate::from_ymd_opt(2025, 1, 8).unwrap(),
+            )],
+            exceptions: vec![
+                moscow("20250111T130000"),
+                GoogleEventTime::DateTime {
+                    date_time: Utc.with_ymd_and_hms(2025, 1, 18, 10, 0, 0).unwrap(),
+                    time_zone: None,
+                },
+            ],
+        };
+        assert_eq!(
+            GoogleRecurrence::from_lines(&recurrence.to_lines()),
+            recurrence
+        );
+    }
+
+    #[test]
+    fn parses_value_lists_and_skips_unknown_lines() {
+        let lines = [
+            "EXDATE;TZID=Europe/Moscow:20250111T130000,20250118T130000",
+            "EXRULE:FREQ=DAILY",
+            "not a line",
+        ]
+        .map(ToOwned::to_owned);
+        let recurrence = GoogleRecurrence::from_lines(&lines);
+        assert!(recurrence.rules.is_empty());
+        assert!(recurrence.dates.is_empty());
+        assert_eq!(
+            recurrence.exceptions,
+            vec![moscow("20250111T130000"), moscow("20250118T130000")]
+        );
+    }
+}
@@ -1,19 +1,116 @@
+use async_trait::async_trait;
+use color_eyre::eyre::eyre;
 use color_eyre::Report as AnyError;
 use reqwest::{Response, StatusCode};
+use serde::Deserialize;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ClientError {
     #[error("Access token has expired.")]
     TokenExpired,
+    #[error("Not authorized: {message}.")]
+    Unauthorized { message: String },
     #[error("Could not parse: {error}.")]
     WrongFormat { error: AnyError },
     #[error("Item with id {id} already exists.")]
     AlreadyExists { id: String },
+    #[error("Class with id {id} is full.")]
+    ClassFull { id: String },
+    #[error("Booking for class with id {id} is closed.")]
+    BookingClosed { id: String },
+    #[error("Booking for class with id {id} can not be cancelled anymore.")]
+    CancellationClosed { id: String },
+    #[error("Not enough membership credits: {message}.")]
+    NotEnoughMembershipCredits { message: String },
+    #[error("Class with id {id} does not exist.")]
+    ClassNotFound { id: String },
+    #[error("Too many requests, try again later.")]
+    RateLimited,
     #[error("Unexpected error: {error}.")]
     Other { error: AnyError },
 }
 
+/// Lowercase phrases of known studio error messages (Russian and English),
+/// whole phrases so that unrelated messages mentioning e.g. a membership are not misread
+const ALREADY_EXISTS_MESSAGES: [&str; 3] = ["уже записан", "already signed up", "already booked"];
+const CLASS_FULL_MESSAGES: [&str; 5] = [
+    "нет свободных мест",
+    "свободных мест нет",
+    "все места заняты",
+    "no free places",
+    "class is full",
+];
+const BOOKING_CLOSED_MESSAGES: [&str; 4] = [
+    "запись закрыта",
+    "запись на занятие закрыта",
+    "запись окончена",
+    "booking is closed",
+];
+const CANCELLATION_CLOSED_MESSAGES: [&str; 4] = [
+    "отмена невозможна",
+    "нельзя отменить",
+    "cancellation is closed",
+    "too late to cancel",
+];
+const NO_CREDITS_MESSAGES: [&str; 6] = [
+    "недостаточно средств",
+    "недостаточно занятий",
+    "нет действующего абонемента",
+    "not enough credits",
+    "not enough membership credits",
+    "no active membership",
+];
+const CLASS_NOT_FOUND_MESSAGES: [&str; 5] = [
+    "занятие не найдено",
+    "занятие не существует",
+    "class not found",
+    "class does not exist",
+    "schedule item not found",
+];
+const RATE_LIMITED_MESSAGES: [&str; 2] = ["слишком много запросов", "too many requests"];
+const UNAUTHORIZED_MESSAGES: [&str; 5] = [
+    "необходимо авторизоваться",
+    "требуется авторизация",
+    "неверный токен",
+    "invalid token",
+    "not authorized",
+];
+
+impl ClientError {
+    /// Classifies an error message returned by a studio API.
+    pub fn from_message(message: &str, id: Option<String>) -> Self {
+        let lowercase = message.to_lowercase();
+        let matches = |fragments: &[&str]| fragments.iter().any(|x| lowercase.contains(x));
+        let id = id.unwrap_or_default();
+        if matches(&ALREADY_EXISTS_MESSAGES) {
+            ClientError::AlreadyExists { id }
+        } else if matches(&CLASS_FULL_MESSAGES) {
+            ClientError::ClassFull { id }
+        } else if matches(&CANCELLATION_CLOSED_MESSAGES) {
+            ClientError::CancellationClosed { id }
+        } else if matches(&BOOKING_CLOSED_MESSAGES) {
+            ClientError::BookingClosed { id }
+        } else if matches(&CLASS_NOT_FOUND_MESSAGES) {
+            ClientError::ClassNotFound { id }
+        } else if matches(&NO_CREDITS_MESSAGES) {
+            ClientError::NotEnoughMembershipCredits {
+                message: message.to_owned(),
+            }
+        } else if matches(&RATE_LIMITED_MESSAGES) {
+            ClientError::RateLimited
+        } else if matches(&UNAUTHORIZED_MESSAGES) {
+            ClientError::Unauthorized {
+                message: message.to_owned(),
+            }
+        } else {
+            ClientError::Other {
+                error: eyre!("{message}"),
+            }
+        }
+    }
+}
+
 impl From<reqwest::Error> for ClientError {
     fn from(value: reqwest::Error) -> Self {
         ClientError::Other {
@@ -38,19 +135,41 @@ impl From<uuid::Error> for ClientError {
     }
 }
 
+#[async_trait]
 pub trait ToClientError {
-    fn map_error(self, id: Option<String>) -> Result<Self, ClientError>
+    async fn map_error(self, id: Option<String>) -> Result<Self, ClientError>
     where
         Self: Sized;
 }
 
+/// JSON error body returned by mobifitness and similar studio APIs.
+#[derive(Debug, Clone, Deserialize)]
+struct ErrorMessageResponse {
+    #[serde(alias = "error", alias = "errorMessage")]
+    message: String,
+}
+
+#[async_trait]
 impl ToClientError for Response {
-    fn map_error(self, _id: Option<String>) -> Result<Self, ClientError> {
+    async fn map_error(self, id: Option<String>) -> Result<Self, ClientError> {
         if let Err(err) = self.error_for_status_ref() {
-            return match self.status() {
-                StatusCode::UNAUTHORIZED => Err(ClientError::TokenExpired),
-                _ => Err(ClientError::Other { error: err.into() }),
-            };
+            let status = self.status();
+            let body = self.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<ErrorMessageResponse>(&body)
+                .map(|x| x.message)
+                .ok();
+            return Err(match (status, message) {
+                (StatusCode::UNAUTHORIZED, _) => ClientError::TokenExpired,
+                (StatusCode::FORBIDDEN, message) => ClientError::Unauthorized {
+                    message: message.unwrap_or(body),
+                },
+                (StatusCode::TOO_MANY_REQUESTS, _) => ClientError::RateLimited,
+                (_, Some(message)) => ClientError::from_message(&message, id),
+                (StatusCode::NOT_FOUND, None) if id.is_some() => ClientError::ClassNotFound {
+                    id: id.unwrap_or_default(),
+                },
+                _ => ClientError::Other { error: err.into() },
+            });
         }
         Ok(self)
     }
@@ -93,3 +212,80 @@ impl From<chrono::ParseError> for ClassParseError<'static> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_full_phrases() {
+        assert!(matches!(
+            ClientError::from_message("Недостаточно средств на счёте", None),
+            ClientError::NotEnoughMembershipCredits { .. }
+        ));
+        assert!(matches!(
+            ClientError::from_message("Занятие не найдено", Some("1".to_owned())),
+            ClientError::ClassNotFound { .. }
+        ));
+        assert!(matches!(
+            ClientError::from_message("Invalid token", None),
+            ClientError::Unauthorized { .. }
+        ));
+    }
+
+    #[test]
+    fn classifies_full_classes() {
+        for message in [
+            "Нет свободных мест",
+            "Свободных мест нет.",
+            "К сожалению, все места заняты",
+            "No free places left",
+            "This class is full",
+        ] {
+            assert!(
+                matches!(
+                    ClientError::from_message(message, Some("1".to_owned())),
+                    ClientError::ClassFull { .. }
+                ),
+                "{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn does_not_take_near_misses_for_full_classes() {
+        for message in [
+            "Парковочных мест нет",
+            "Мест нет в гардеробе, приходите без верхней одежды",
+            "Your schedule is full",
+            "Payment is fully processed",
+            "Места для коврика заняты",
+        ] {
+            assert!(
+                !matches!(
+                    ClientError::from_message(message, Some("1".to_owned())),
+                    ClientError::ClassFull { .. }
+                ),
+                "{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_unrelated_messages_alone() {
+        for message in [
+            "Абонемент не найден",
+            "Your membership renews tomorrow",
+            "Not enough rooms configured",
+            "Refresh token rotated",
+        ] {
+            assert!(
+                matches!(
+                    ClientError::from_message(message, None),
+                    ClientError::Other { .. }
+                ),
+                "{message}"
+            );
+        }
+    }
+}
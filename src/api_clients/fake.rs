@@ -0,0 +1,100 @@
+//! Studio client counting its requests, for tests.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Duration;
+
+use crate::api_clients::errors::ClientError;
+use crate::api_clients::models::{Class, UtcDateTime};
+use crate::api_clients::StudioCRUD;
+
+/// Requests running at the same time, may be shared by several clients.
+#[derive(Debug, Default)]
+pub struct InFlight {
+    current: AtomicUsize,
+    pub max: AtomicUsize,
+}
+
+/// Every day has a single "Yoga" class starting at the requested time.
+#[derive(Debug, Default)]
+pub struct FakeStudio {
+    pub name: String,
+    pub day_requests: AtomicUsize,
+    /// Shared so they can be read once the client is boxed
+    pub user_requests: Arc<AtomicUsize>,
+    pub unavailable: Arc<AtomicBool>,
+    pub sign_ups: AtomicUsize,
+    /// Number of day requests failing before they succeed
+    pub failures: AtomicUsize,
+    pub in_flight: Arc<InFlight>,
+}
+
+impl FakeStudio {
+    pub fn new(name: &str) -> Self {
+        FakeStudio {
+            name: name.to_owned(),
+            ..FakeStudio::default()
+        }
+    }
+
+    async fn request(&self) {
+        let current = self.in_flight.current.fetch_add(1, Ordering::SeqCst) + 1;
+        self.in_flight.max.fetch_max(current, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        self.in_flight.current.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl StudioCRUD for FakeStudio {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn get_user_classes(&self) -> Result<Vec<Class>, ClientError> {
+        self.user_requests.fetch_add(1, Ordering::SeqCst);
+        if self.unavailable.load(Ordering::SeqCst) {
+            return Err(ClientError::RateLimited);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn list_day_classes(&self, day: &UtcDateTime) -> Result<Vec<Class>, ClientError> {
+        self.day_requests.fetch_add(1, Ordering::SeqCst);
+        self.request().await;
+        let failing = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| x.checked_sub(1));
+        if failing.is_ok() {
+            return Err(ClientError::RateLimited);
+        }
+        Ok(vec![Class {
+            id: day.timestamp().to_string(),
+            name: "Yoga".to_owned(),
+            studio: self.name.clone(),
+            instructor: String::new(),
+            instructor_id: None,
+            location: None,
+            room: None,
+            booking_url: None,
+            start: *day,
+            end: *day + Duration::hours(1),
+        }])
+    }
+
+    async fn sign_up_for_class(&self, _class: &Class) -> Result<(), ClientError> {
+        self.sign_ups.fetch_add(1, Ordering::SeqCst);
+        self.request().await;
+        Ok(())
+    }
+
+    async fn cancel_class(&self, _class: &Class) -> Result<(), ClientError> {
+        Ok(())
+    }
+
+    fn cancellation_deadline(&self) -> Duration {
+        Duration::hours(3)
+    }
+}
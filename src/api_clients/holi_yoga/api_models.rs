@@ -1,6 +1,7 @@
 use std::collections::HashMap;
+use std::fmt::Display;
 
-use color_eyre::eyre::eyre;
+use async_trait::async_trait;
 use serde::Deserialize;
 use uuid::Uuid;
 
@@ -97,6 +98,8 @@ pub struct HoliUserClassResponse {
 #[derive(Debug, Clone, Deserialize)]
 pub struct HoliClubInfo {
     pub time_zone: String,
+    #[serde(default)]
+    pub address: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -110,16 +113,18 @@ pub enum HoliMethods {
     GetUserClasses,
     GetSchedule,
     SignUp,
+    Cancel,
 }
 
-impl ToString for HoliMethods {
-    fn to_string(&self) -> String {
-        match self {
+impl Display for HoliMethods {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let method = match self {
             HoliMethods::GetUserClasses => "getUserApp",
             HoliMethods::GetSchedule => "getFitCalendar",
             HoliMethods::SignUp => "setApp",
-        }
-        .to_owned()
+            HoliMethods::Cancel => "delApp",
+        };
+        write!(f, "{method}")
     }
 }
 
@@ -137,6 +142,9 @@ pub(crate) trait HoliResponseTrait {
             .parse()
             .map_err(|_| ClassParseError::UnknownTimezone { time_zone })
     }
+    fn address(&self, club_id: &Uuid) -> Option<String> {
+        self.clubs().get(club_id).and_then(|x| x.address.clone())
+    }
     fn clubs(&self) -> &HashMap<Uuid, HoliClubInfo>;
 }
 
@@ -152,22 +160,14 @@ impl HoliResponseTrait for HoliScheduleResponse {
     }
 }
 
+#[async_trait]
 impl ToClientError for HoliUserClassResponse {
-    fn map_error(self, id: Option<String>) -> Result<Self, ClientError>
+    async fn map_error(self, id: Option<String>) -> Result<Self, ClientError>
     where
         Self: Sized,
     {
         if self.is_error {
-            match self.result.as_str() {
-                "Клиент уже записан на занятие" => {
-                    return Err(ClientError::AlreadyExists { id: id.unwrap() })
-                }
-                _ => {
-                    return Err(ClientError::Other {
-                        error: eyre!("{}", self.result),
-                    })
-                }
-            }
+            return Err(ClientError::from_message(&self.result, id));
         }
         Ok(self)
     }
@@ -1,3 +1,6 @@
+pub const HOLI_STUDIO_NAME: &str = "Holi Yoga";
+pub const HOLI_CANCELLATION_DEADLINE_HOURS: i64 = 12;
+
 pub const CLASSES_CSS_SELECTOR: &str = ".list-history_rz:not([data-history-list])";
 pub const CLASS_CSS_SELECTOR: &str = ".item-hist_rz";
 pub const CLASS_ID_ATTR: &str = "data-id";
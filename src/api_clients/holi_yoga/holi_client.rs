@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use chrono::Duration;
 use reqwest::header::{self, HeaderMap, HeaderValue};
 use reqwest::Url;
 use reqwest::{Client, ClientBuilder};
@@ -6,6 +7,7 @@ use scraper::Html;
 use uuid::Uuid;
 
 use crate::api_clients::holi_yoga::api_models::{HoliResponseTrait, HoliScheduleResponse};
+use crate::api_clients::holi_yoga::consts::{HOLI_CANCELLATION_DEADLINE_HOURS, HOLI_STUDIO_NAME};
 use crate::api_clients::holi_yoga::parse::parse_schedule;
 use crate::api_clients::models::{Class, UtcDateTime};
 use crate::api_clients::StudioCRUD;
@@ -26,6 +28,7 @@ pub struct HoliClient {
     base_form: RequestForm,
     login_info: HoliLoginData,
     club_id: Uuid,
+    booking_url: Option<String>,
 }
 
 impl HoliClient {
@@ -34,6 +37,7 @@ impl HoliClient {
         club_id: Uuid,
         username: String,
         password: String,
+        booking_url: Option<String>,
     ) -> Result<Self, ClientError> {
         let mut headers = HeaderMap::new();
         headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
@@ -56,6 +60,7 @@ impl HoliClient {
                 api_key,
             },
             club_id,
+            booking_url,
         };
         holi_client.login().await?;
         Ok(holi_client)
@@ -68,7 +73,8 @@ impl HoliClient {
             .form(self.login_info.form().unwrap())
             .send()
             .await?
-            .map_error(None)?
+            .map_error(None)
+            .await?
             .json::<HoliLoginResponse>()
             .await?;
         self.base_form.insert_param("token", &response.token);
@@ -88,10 +94,12 @@ impl HoliClient {
             .form(request_form.unwrap())
             .send()
             .await?
-            .map_error(None)?
+            .map_error(None)
+            .await?
             .json::<HoliUserClassResponse>()
             .await?
             .map_error(None)
+            .await
     }
 
     async fn get_day_schedule(
@@ -113,7 +121,8 @@ impl HoliClient {
             .form(request_form.unwrap())
             .send()
             .await?
-            .map_error(None)?
+            .map_error(None)
+            .await?
             .json::<HoliScheduleResponse>()
             .await?)
     }
@@ -132,38 +141,80 @@ impl HoliClient {
             .form(request_form.unwrap())
             .send()
             .await?
-            .map_error(None)?
+            .map_error(None)
+            .await?
             .json::<HoliUserClassResponse>()
             .await?
-            .map_error(Some(class_id.to_string()))?;
+            .map_error(Some(class_id.to_string()))
+            .await?;
         log::debug!("Sign up response: {:?}", response);
         Ok(response)
     }
+
+    async fn delete_user_class(
+        &self,
+        class_id: Uuid,
+    ) -> Result<HoliUserClassResponse, ClientError> {
+        let mut request_form = HoliRequestData {
+            method: HoliMethods::Cancel,
+            app_id: Some(class_id),
+        }
+        .form();
+        request_form.extend(&self.base_form);
+        log::debug!("Cancel form: {:?}", request_form);
+        let response = self
+            .client
+            .post(self.base_url.clone())
+            .form(request_form.unwrap())
+            .send()
+            .await?
+            .map_error(None)
+            .await?
+            .json::<HoliUserClassResponse>()
+            .await?
+            .map_error(Some(class_id.to_string()))
+            .await?;
+        log::debug!("Cancel response: {:?}", response);
+        Ok(response)
+    }
+
+    fn add_details<T: HoliResponseTrait>(
+        &self,
+        mut classes: Vec<Class>,
+        response: &T,
+    ) -> Vec<Class> {
+        let address = response.address(&self.club_id);
+        for class in &mut classes {
+            class.location.clone_from(&address);
+            class.set_booking_url(self.booking_url.as_ref());
+        }
+        classes
+    }
 }
 
 #[async_trait]
 impl StudioCRUD for HoliClient {
     fn name(&self) -> String {
-        "Holi Yoga".to_owned()
+        HOLI_STUDIO_NAME.to_owned()
     }
 
     async fn get_user_classes(&self) -> Result<Vec<Class>, ClientError> {
         let response = self.list_user_classes().await?;
-        parse_user_classes(
+        let classes = parse_user_classes(
             &Html::parse_document(&response.result),
             response.time_zone(&self.club_id)?,
-        )
-        .map_err(Into::into)
+        )?;
+        Ok(self.add_details(classes, &response))
     }
 
     async fn list_day_classes(&self, day: &UtcDateTime) -> Result<Vec<Class>, ClientError> {
         let response = self.get_day_schedule(day).await?;
-        parse_schedule(
+        let classes = parse_schedule(
             &Html::parse_document(&response.slider.schedule_html),
             day.date_naive(),
             response.time_zone(&self.club_id)?,
-        )
-        .map_err(Into::into)
+        )?;
+        Ok(self.add_details(classes, &response))
     }
 
     async fn sign_up_for_class(&self, class: &Class) -> Result<(), ClientError> {
@@ -171,4 +222,14 @@ impl StudioCRUD for HoliClient {
         log::info!("Signed up for {class}");
         Ok(())
     }
+
+    async fn cancel_class(&self, class: &Class) -> Result<(), ClientError> {
+        self.delete_user_class(Uuid::parse_str(&class.id)?).await?;
+        log::info!("Cancelled booking for {class}");
+        Ok(())
+    }
+
+    fn cancellation_deadline(&self) -> Duration {
+        Duration::hours(HOLI_CANCELLATION_DEADLINE_HOURS)
+    }
 }
@@ -10,7 +10,7 @@ use crate::api_clients::{errors::ClassParseError, holi_yoga::consts::CLASS_ID_CS
 
 use super::consts::{
     CLASSES_CSS_SELECTOR, CLASS_CSS_SELECTOR, CLASS_DATE_SELECTOR, CLASS_DURATION_SELECTOR,
-    CLASS_NAME_CSS_SELECTOR, CLASS_START_TIME_SELECTOR, SCHEDULE_CSS_SELECTOR,
+    CLASS_NAME_CSS_SELECTOR, CLASS_START_TIME_SELECTOR, HOLI_STUDIO_NAME, SCHEDULE_CSS_SELECTOR,
 };
 
 pub fn parse_user_classes(
@@ -42,16 +42,14 @@ pub fn parse_user_classes(
         .unwrap();
 
         let name_element = get_element(&class_element, CLASS_NAME_CSS_SELECTOR, "class name")?;
-        let class_name = format!(
-            "{}",
-            name_element
-                .inner_html()
-                .split("<br>")
-                .collect::<Vec<&str>>()
-                .first()
-                .unwrap()
-                .trim()
-        );
+        let class_name = name_element
+            .inner_html()
+            .split("<br>")
+            .collect::<Vec<&str>>()
+            .first()
+            .unwrap()
+            .trim()
+            .to_owned();
 
         let instructor_element = get_element(
             &class_element,
@@ -97,7 +95,12 @@ pub fn parse_user_classes(
         let class = Class {
             id: class_id,
             name: class_name,
+            studio: HOLI_STUDIO_NAME.to_owned(),
             instructor,
+            instructor_id: None,
+            location: None,
+            room: None,
+            booking_url: None,
             start,
             end,
         };
@@ -126,7 +129,7 @@ pub fn parse_schedule(
     for tr_element in classes_element.select(&class_selector) {
         let ul_elements = get_elements(&tr_element, CLASS_ID_CSS_SELECTOR);
         for class_element in ul_elements {
-            classes.push(parse_schedule_class(&class_element, &date, time_zone)?);
+            classes.push(parse_schedule_class(&class_element, date, time_zone)?);
         }
     }
     Ok(classes)
@@ -134,7 +137,7 @@ pub fn parse_schedule(
 
 fn parse_schedule_class(
     class_element: &ElementRef,
-    date: &NaiveDate,
+    date: NaiveDate,
     time_zone: chrono_tz::Tz,
 ) -> Result<Class, ClassParseError<'static>> {
     let class_id: String = serde_json::from_str(&format!(
@@ -190,7 +193,12 @@ fn parse_schedule_class(
     let class = Class {
         id: class_id,
         name: class_name,
+        studio: HOLI_STUDIO_NAME.to_owned(),
         instructor,
+        instructor_id: None,
+        location: None,
+        room: None,
+        booking_url: None,
         start,
         end,
     };
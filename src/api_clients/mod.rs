@@ -1,11 +1,15 @@
 use async_trait::async_trait;
+use chrono::Duration;
 use errors::ClientError;
 use models::{Class, UtcDateTime};
 
 pub mod errors;
+#[cfg(test)]
+pub mod fake;
 pub mod holi_yoga;
 pub mod models;
 pub mod plastilin;
+pub mod schedule_cache;
 
 #[async_trait]
 pub trait StudioCRUD {
@@ -13,4 +17,7 @@ pub trait StudioCRUD {
     async fn get_user_classes(&self) -> Result<Vec<Class>, ClientError>;
     async fn list_day_classes(&self, day: &UtcDateTime) -> Result<Vec<Class>, ClientError>;
     async fn sign_up_for_class(&self, class: &Class) -> Result<(), ClientError>;
+    async fn cancel_class(&self, class: &Class) -> Result<(), ClientError>;
+    /// How long before the start of a class its booking can still be cancelled.
+    fn cancellation_deadline(&self) -> Duration;
 }
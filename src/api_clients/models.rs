@@ -1,8 +1,27 @@
+use std::collections::HashMap;
 use std::fmt::Display;
 
-use chrono::{DateTime, Local, Utc};
-use email_address::EmailAddress;
-use google_api::models::{GoogleEvent, GoogleEventListParams, GoogleEventPost};
+use chrono::{DateTime, Duration, Local, Utc};
+use google_api::models::{
+    GoogleEvent, GoogleEventListParams, GoogleEventPost, GoogleEventSource, GoogleEventTime,
+};
+
+use crate::models::StudioSyncConfig;
+
+/// Private extended properties identifying the class an event was created for.
+pub const CLASS_ID_PROPERTY: &str = "gcuClassId";
+pub const STUDIO_PROPERTY: &str = "gcuStudio";
+pub const EVENT_KIND_PROPERTY: &str = "gcuEventKind";
+/// Marks events managed by the utility, used to list only them.
+pub const MANAGED_PROPERTY: &str = "gcuManaged";
+pub const MANAGED_VALUE: &str = "true";
+/// Marks events of bookings cancelled by the studio.
+pub const CANCELLED_PROPERTY: &str = "gcuCancelled";
+pub const CANCELLED_PREFIX: &str = "CANCELLED: ";
+/// Marks events the utility deletes itself, so two-way sync does not take them for user deletions.
+pub const DELETED_PROPERTY: &str = "gcuDeletedBySync";
+/// Hashes of the field values last written by the utility, see `ownership`.
+pub const HASHES_PROPERTY: &str = "gcuHashes";
 
 pub type UtcDateTime = DateTime<Utc>;
 
@@ -10,11 +29,48 @@ pub type UtcDateTime = DateTime<Utc>;
 pub struct Class {
     pub id: String,
     pub name: String,
+    pub studio: String,
     pub instructor: String,
+    pub instructor_id: Option<String>,
+    pub location: Option<String>,
+    pub room: Option<String>,
+    pub booking_url: Option<String>,
     pub start: UtcDateTime,
     pub end: UtcDateTime,
 }
 
+/// Events managed for a single class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Class,
+    TravelTo,
+    TravelBack,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Class => "class",
+            EventKind::TravelTo => "travelTo",
+            EventKind::TravelBack => "travelBack",
+        }
+    }
+
+    /// Kind of a managed event, events created before kinds were stored are classes.
+    pub fn of(event: &GoogleEvent) -> Option<Self> {
+        match event
+            .private_properties
+            .get(EVENT_KIND_PROPERTY)
+            .map(String::as_str)
+        {
+            None | Some("class") => Some(EventKind::Class),
+            Some("travelTo") => Some(EventKind::TravelTo),
+            Some("travelBack") => Some(EventKind::TravelBack),
+            Some(_) => None,
+        }
+    }
+}
+
 impl PartialEq for Class {
     fn eq(&self, other: &Self) -> bool {
         self.name == other.name
@@ -27,8 +83,8 @@ impl PartialEq for Class {
 impl PartialEq<&GoogleEvent> for &Class {
     fn eq(&self, other: &&GoogleEvent) -> bool {
         if let Some(name) = &other.summary {
-            if let Some(start) = other.start {
-                if let Some(end) = other.end {
+            if let Some(start) = other.start.and_then(|x| x.date_time()) {
+                if let Some(end) = other.end.and_then(|x| x.date_time()) {
                     return self.name.eq(name) && self.start == start && self.end == end;
                 }
             }
@@ -49,21 +105,178 @@ impl Display for Class {
 }
 
 impl Class {
-    pub fn to_google_post(&self) -> GoogleEventPost {
+    /// Start and end of the class including its travel events.
+    pub fn span(&self, config: &StudioSyncConfig) -> (UtcDateTime, UtcDateTime) {
+        let (before, after) = config
+            .travel
+            .as_ref()
+            .map_or((0, 0), |travel| (travel.before, travel.after));
+        (
+            self.start - Duration::minutes(before.into()),
+            self.end + Duration::minutes(after.into()),
+        )
+    }
+
+    /// The class event followed by travel events if travel time is configured for the studio.
+    pub fn to_google_posts(&self, config: &StudioSyncConfig) -> Vec<(EventKind, GoogleEventPost)> {
+        let post = self.to_google_post(config);
+        let mut posts = Vec::new();
+        if let Some(travel) = &config.travel {
+            if travel.before > 0 {
+                posts.push((
+                    EventKind::TravelTo,
+                    self.travel_post(
+                        config,
+                        &post,
+                        EventKind::TravelTo,
+                        format!("Travel to {}", self.studio),
+                        self.start - Duration::minutes(travel.before.into()),
+                        self.start,
+                    ),
+                ));
+            }
+            if travel.after > 0 {
+                posts.push((
+                    EventKind::TravelBack,
+                    self.travel_post(
+                        config,
+                        &post,
+                        EventKind::TravelBack,
+                        format!("Travel back from {}", self.studio),
+                        self.end,
+                        self.end + Duration::minutes(travel.after.into()),
+                    ),
+                ));
+            }
+        }
+        posts.insert(0, (EventKind::Class, post));
+        posts
+    }
+
+    fn travel_post(
+        &self,
+        config: &StudioSyncConfig,
+        class_post: &GoogleEventPost,
+        kind: EventKind,
+        summary: String,
+        start: UtcDateTime,
+        end: UtcDateTime,
+    ) -> GoogleEventPost {
+        let mut private_properties = class_post.private_properties.clone();
+        private_properties.insert(EVENT_KIND_PROPERTY.to_owned(), kind.as_str().to_owned());
         GoogleEventPost {
-            summary: self.name.clone(),
-            description: None,
-            start: self.start,
-            end: self.end,
+            summary,
+            description: Some(self.to_string()),
+            location: self.location.clone(),
+            source: None,
+            start: event_time(start, config),
+            end: event_time(end, config),
+            private_properties,
+            color_id: class_post.color_id.clone(),
+            reminders: Some(Vec::new()),
+            recurrence: None,
         }
     }
 
-    pub fn to_google_list_params(&self, creator_email: &EmailAddress) -> GoogleEventListParams {
-        GoogleEventListParams {
-            search_param: Some(self.name.clone()),
-            start: Some(self.start),
-            end: Some(self.end),
-            creator_email: Some(creator_email.to_owned()),
+    pub fn to_google_post(&self, config: &StudioSyncConfig) -> GoogleEventPost {
+        GoogleEventPost {
+            summary: config
+                .summary
+                .as_ref()
+                .map_or_else(|| self.name.clone(), |x| self.render(x)),
+            description: Some(
+                config
+                    .description
+                    .as_ref()
+                    .map_or_else(|| self.description(), |x| self.render(x)),
+            ),
+            location: self.location.clone(),
+            source: self.booking_url.clone().map(|url| GoogleEventSource {
+                title: self.studio.clone(),
+                url,
+            }),
+            start: event_time(self.start, config),
+            end: event_time(self.end, config),
+            private_properties: HashMap::from([
+                (MANAGED_PROPERTY.to_owned(), MANAGED_VALUE.to_owned()),
+                (CLASS_ID_PROPERTY.to_owned(), self.id.clone()),
+                (STUDIO_PROPERTY.to_owned(), self.studio.clone()),
+                (
+                    EVENT_KIND_PROPERTY.to_owned(),
+                    EventKind::Class.as_str().to_owned(),
+                ),
+            ]),
+            color_id: config.color(&self.name),
+            reminders: config.reminders(&self.name),
+            recurrence: None,
+        }
+    }
+
+    /// Whether `event` is the `kind` event created for this class.
+    pub fn is_event(&self, event: &GoogleEvent, kind: EventKind) -> bool {
+        if EventKind::of(event) != Some(kind) {
+            return false;
+        }
+        match (
+            event.private_properties.get(CLASS_ID_PROPERTY),
+            event.private_properties.get(STUDIO_PROPERTY),
+        ) {
+            (Some(id), Some(studio)) => self.id.eq(id) && self.studio.eq(studio),
+            // events created before class ids were stored
+            _ => kind == EventKind::Class && self == event,
+        }
+    }
+
+    fn render(&self, template: &str) -> String {
+        template
+            .replace("{name}", &self.name)
+            .replace("{instructor}", &self.instructor)
+            .replace("{studio}", &self.studio)
+            .replace(
+                "{duration}",
+                &(self.end - self.start).num_minutes().to_string(),
+            )
+            .replace("{id}", &self.id)
+            .replace("{location}", self.location.as_deref().unwrap_or_default())
+            .replace("{room}", self.room.as_deref().unwrap_or_default())
+            .replace(
+                "{bookingUrl}",
+                self.booking_url.as_deref().unwrap_or_default(),
+            )
+    }
+
+    fn description(&self) -> String {
+        let mut lines = vec![format!("Studio: {}", self.studio)];
+        if !self.instructor.is_empty() {
+            lines.push(format!("Instructor: {}", self.instructor));
         }
+        if let Some(room) = &self.room {
+            lines.push(format!("Room: {room}"));
+        }
+        if let Some(url) = &self.booking_url {
+            lines.push(format!("Booking: {url}"));
+        }
+        lines.join("\n")
+    }
+
+    /// Fills in the booking link from a template where `{id}` is replaced with the class id.
+    pub fn set_booking_url(&mut self, template: Option<&String>) {
+        self.booking_url = template.map(|x| x.replace("{id}", &self.id));
+    }
+
+    pub fn to_google_list_params(&self) -> GoogleEventListParams {
+        GoogleEventListParams::new()
+            .search(&self.name)
+            .start(self.start)
+            .end(self.end)
+            .private_property(MANAGED_PROPERTY, MANAGED_VALUE)
+            .private_property(CLASS_ID_PROPERTY, &self.id)
+    }
+}
+
+fn event_time(date_time: UtcDateTime, config: &StudioSyncConfig) -> GoogleEventTime {
+    GoogleEventTime::DateTime {
+        date_time,
+        time_zone: config.timezone,
     }
 }
@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::api_clients::models::{Class, UtcDateTime};
 
+use super::consts::PLASTILIN_STUDIO_NAME;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct UserClassListResponse {
     pub schedules: Vec<ScheduleResponse>,
@@ -22,6 +24,8 @@ pub struct ClassResponse {
     pub duration_min: u8,
     pub activity: ActivityResponse,
     pub trainers: Vec<TrainerResponse>,
+    #[serde(default)]
+    pub room: Option<RoomResponse>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -38,6 +42,18 @@ pub struct TrainerResponse {
     pub name: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoomResponse {
+    #[serde(rename = "title")]
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClubResponse {
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PostUserClass {
@@ -48,16 +64,16 @@ pub struct PostUserClass {
 
 impl From<ClassResponse> for Class {
     fn from(value: ClassResponse) -> Self {
+        let trainer = value.trainers.first();
         Self {
             id: value.id.to_string(),
             name: value.activity.name.clone(),
-            instructor: {
-                if let Some(trainer) = value.trainers.first() {
-                    trainer.id.clone()
-                } else {
-                    String::new()
-                }
-            },
+            studio: PLASTILIN_STUDIO_NAME.to_owned(),
+            instructor: trainer.map(|x| x.name.clone()).unwrap_or_default(),
+            instructor_id: trainer.map(|x| x.id.clone()),
+            location: None,
+            room: value.room.map(|x| x.name),
+            booking_url: None,
             start: value.start_datetime,
             end: value.start_datetime + Duration::minutes(i64::from(value.duration_min)),
         }
@@ -68,7 +84,7 @@ impl From<&Class> for PostUserClass {
     fn from(value: &Class) -> Self {
         PostUserClass {
             class_id: value.id.clone(),
-            trainer_id: value.instructor.clone(),
+            trainer_id: value.instructor_id.clone().unwrap_or_default(),
         }
     }
 }
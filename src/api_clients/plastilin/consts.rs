@@ -0,0 +1,2 @@
+pub const PLASTILIN_STUDIO_NAME: &str = "Plastilin";
+pub const PLASTILIN_CANCELLATION_DEADLINE_HOURS: i64 = 3;
@@ -1,2 +1,3 @@
 pub mod api_models;
+pub mod consts;
 pub mod plastilin_client;
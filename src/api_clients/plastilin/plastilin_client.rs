@@ -1,13 +1,17 @@
 use async_trait::async_trait;
-use chrono::{Datelike, NaiveDate, TimeDelta};
+use chrono::{Datelike, Duration, NaiveDate};
 use reqwest::header::{self, HeaderMap, HeaderValue};
 use reqwest::Url;
 use reqwest::{Client, ClientBuilder};
+use tokio::sync::OnceCell;
 
 use crate::api_clients::models::{Class, UtcDateTime};
 use crate::api_clients::StudioCRUD;
 
-use super::api_models::{ClassResponse, PostUserClass, ScheduleResponse, UserClassListResponse};
+use super::api_models::{
+    ClassResponse, ClubResponse, PostUserClass, ScheduleResponse, UserClassListResponse,
+};
+use super::consts::{PLASTILIN_CANCELLATION_DEADLINE_HOURS, PLASTILIN_STUDIO_NAME};
 use crate::api_clients::errors::{ClientError, ToClientError};
 
 const PLASTILIN_API_URL: &str = "https://mobifitness.ru/api/v8/";
@@ -17,10 +21,16 @@ pub struct PlastilinClient {
     client: Client,
     base_url: Url,
     club_id: u16,
+    club: OnceCell<Option<ClubResponse>>,
+    booking_url: Option<String>,
 }
 
 impl PlastilinClient {
-    pub fn new(token: &str, club_id: u16) -> Result<Self, ClientError> {
+    pub fn new(
+        token: &str,
+        club_id: u16,
+        booking_url: Option<String>,
+    ) -> Result<Self, ClientError> {
         let mut headers = HeaderMap::new();
         headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
         headers.insert(
@@ -37,16 +47,57 @@ impl PlastilinClient {
             client,
             base_url: Url::parse(PLASTILIN_API_URL)?,
             club_id,
+            club: OnceCell::new(),
+            booking_url,
         })
     }
 
+    async fn get_club(&self) -> Result<ClubResponse, ClientError> {
+        Ok(self
+            .client
+            .get(self.base_url.join(&format!("club/{}.json", self.club_id))?)
+            .send()
+            .await?
+            .map_error(None)
+            .await?
+            .json::<ClubResponse>()
+            .await?)
+    }
+
+    /// Club info is only used for event details, so a failed request is logged and not retried.
+    async fn club(&self) -> Option<&ClubResponse> {
+        self.club
+            .get_or_init(|| async {
+                self.get_club()
+                    .await
+                    .inspect_err(|err| log::warn!("Could not get Plastilin club info: {err}"))
+                    .ok()
+            })
+            .await
+            .as_ref()
+    }
+
+    async fn to_classes(&self, responses: Vec<ClassResponse>) -> Vec<Class> {
+        let address = self.club().await.and_then(|x| x.address.clone());
+        responses
+            .into_iter()
+            .map(|response| {
+                let mut class: Class = response.into();
+                class.location.clone_from(&address);
+                class.set_booking_url(self.booking_url.as_ref());
+                class
+            })
+            .collect()
+    }
+
     async fn list_user_classes(&self) -> Result<Vec<ClassResponse>, ClientError> {
         let response = self
             .client
             .get(self.base_url.join("account/schedule.json")?)
             .send()
             .await?
-            .map_error(None)?
+            .map_error(None)
+            .await?
             .json::<UserClassListResponse>()
             .await?;
         if let Some(schedules) = response.schedules.first() {
@@ -59,7 +110,7 @@ impl PlastilinClient {
         let offset = day.weekday().num_days_from_monday();
         let week_number = (NaiveDate::from_ymd_opt(day.year(), day.month(), day.day()).unwrap()
             - NaiveDate::from_ymd_opt(day.year(), 1, 1).unwrap()
-            + TimeDelta::days(offset.into()))
+            + Duration::days(offset.into()))
         .num_days()
             / 7;
 
@@ -72,7 +123,8 @@ impl PlastilinClient {
             .query(&[("year", day.year().into()), ("week", week_number)])
             .send()
             .await?
-            .map_error(None)?
+            .map_error(None)
+            .await?
             .json::<ScheduleResponse>()
             .await?;
         Ok(response.schedule)
@@ -88,7 +140,23 @@ impl PlastilinClient {
             .json(&body)
             .send()
             .await?
-            .map_error(Some(class_id.to_owned()))?;
+            .map_error(Some(class_id.to_owned()))
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_user_class(
+        &self,
+        class_id: &str,
+        body: PostUserClass,
+    ) -> Result<(), ClientError> {
+        self.client
+            .post(self.base_url.join("account/reserve/cancel.json")?)
+            .json(&body)
+            .send()
+            .await?
+            .map_error(Some(class_id.to_owned()))
+            .await?;
         Ok(())
     }
 }
@@ -96,25 +164,17 @@ impl PlastilinClient {
 #[async_trait]
 impl StudioCRUD for PlastilinClient {
     fn name(&self) -> String {
-        "Plastilin".to_owned()
+        PLASTILIN_STUDIO_NAME.to_owned()
     }
 
     async fn get_user_classes(&self) -> Result<Vec<Class>, ClientError> {
-        Ok(self
-            .list_user_classes()
-            .await?
-            .into_iter()
-            .map(Into::into)
-            .collect())
+        let responses = self.list_user_classes().await?;
+        Ok(self.to_classes(responses).await)
     }
 
     async fn list_day_classes(&self, day: &UtcDateTime) -> Result<Vec<Class>, ClientError> {
-        Ok(self
-            .get_day_schedule(day)
-            .await?
-            .into_iter()
-            .map(Into::into)
-            .collect())
+        let responses = self.get_day_schedule(day).await?;
+        Ok(self.to_classes(responses).await)
     }
 
     async fn sign_up_for_class(&self, class: &Class) -> Result<(), ClientError> {
@@ -122,4 +182,14 @@ impl StudioCRUD for PlastilinClient {
         log::info!("Signed up for {class}");
         Ok(())
     }
+
+    async fn cancel_class(&self, class: &Class) -> Result<(), ClientError> {
+        self.delete_user_class(&class.id, class.into()).await?;
+        log::info!("Cancelled booking for {class}");
+        Ok(())
+    }
+
+    fn cancellation_deadline(&self) -> Duration {
+        Duration::hours(PLASTILIN_CANCELLATION_DEADLINE_HOURS)
+    }
 }
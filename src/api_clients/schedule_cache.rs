@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::api_clients::errors::ClientError;
+use crate::api_clients::models::{Class, UtcDateTime};
+use crate::api_clients::StudioCRUD;
+
+type DayKey = (String, NaiveDate);
+
+/// Per-run memoization of day schedules keyed by (studio, date).
+/// Concurrent requests for the same day wait for a single fetch; failed fetches are not cached.
+#[derive(Debug, Default)]
+pub struct ScheduleCache {
+    days: Mutex<HashMap<DayKey, Arc<OnceCell<Vec<Class>>>>>,
+}
+
+impl ScheduleCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn list_day_classes<T>(
+        &self,
+        client: &T,
+        day: &UtcDateTime,
+    ) -> Result<Vec<Class>, ClientError>
+    where
+        T: StudioCRUD + ?Sized,
+    {
+        let cell = self
+            .days
+            .lock()
+            .await
+            .entry((client.name(), day.date_naive()))
+            .or_default()
+            .clone();
+        cell.get_or_try_init(|| client.list_day_classes(day))
+            .await
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::*;
+    use crate::api_clients::fake::FakeStudio;
+
+    #[tokio::test]
+    async fn asks_once_per_studio_and_day() {
+        let cache = ScheduleCache::new();
+        let (first, second) = (FakeStudio::new("First"), FakeStudio::new("Second"));
+        let morning = Utc.with_ymd_and_hms(2025, 1, 6, 7, 0, 0).unwrap();
+        let evening = morning + Duration::hours(12);
+        let (a, b) = tokio::join!(
+            cache.list_day_classes(&first, &morning),
+            cache.list_day_classes(&first, &evening)
+        );
+        // the day's schedule is cached, not the requested time
+        assert_eq!(a.unwrap()[0].start, morning);
+        assert_eq!(b.unwrap()[0].start, morning);
+        assert_eq!(first.day_requests.load(Ordering::SeqCst), 1);
+
+        cache.list_day_classes(&first, &morning).await.unwrap();
+        cache
+            .list_day_classes(&first, &(morning + Duration::days(1)))
+            .await
+            .unwrap();
+        cache.list_day_classes(&second, &morning).await.unwrap();
+        assert_eq!(first.day_requests.load(Ordering::SeqCst), 2);
+        assert_eq!(second.day_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_failures() {
+        let cache = ScheduleCache::new();
+        let studio = FakeStudio::new("First");
+        studio.failures.store(1, Ordering::SeqCst);
+        let day = Utc.with_ymd_and_hms(2025, 1, 6, 7, 0, 0).unwrap();
+        assert!(cache.list_day_classes(&studio, &day).await.is_err());
+        assert!(cache.list_day_classes(&studio, &day).await.is_ok());
+        assert!(cache.list_day_classes(&studio, &day).await.is_ok());
+        assert_eq!(studio.day_requests.load(Ordering::SeqCst), 2);
+    }
+}
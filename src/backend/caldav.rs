@@ -0,0 +1,865 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use color_eyre::{eyre::eyre, Result as AnyResult};
+use google_api::{
+    batch::{GoogleBatchRequest, GoogleEventBatch},
+    models::{
+        GoogleEvent, GoogleEventPatch, GoogleEventPost, GoogleEventSnapshot, GoogleEventTime,
+        GoogleReminder, ReminderMethod,
+    },
+};
+use reqwest::{header, Client, Method, RequestBuilder, Response, StatusCode, Url};
+use serde_json::json;
+use uuid::Uuid;
+
+use super::{CalendarBackend, ChangeResult};
+use crate::api_clients::models::{UtcDateTime, MANAGED_PROPERTY, MANAGED_VALUE};
+use crate::ical::{escape, fold, DATE_TIME_FORMAT};
+
+const CALDAV_NAMESPACE: &str = "urn:ietf:params:xml:ns:caldav";
+const DATE_FORMAT: &str = "%Y%m%d";
+const LOCAL_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+/// Private properties are stored as `X-GCU-PROPERTY;KEY="<key>":<value>`,
+/// property names are case-insensitive so the key goes into a quoted parameter.
+const PROPERTY: &str = "X-GCU-PROPERTY";
+const COLOR_PROPERTY: &str = "X-GCU-COLOR";
+/// Time zone the event is shown in, times themselves are written in UTC
+/// so servers do not need a `VTIMEZONE` for them.
+const TIME_ZONE_PARAMETER: &str = "X-GCU-TZ";
+
+/// CalDAV calendar collection, e.g. a Nextcloud, Fastmail, iCloud or Radicale calendar.
+/// Events are stored as `<uid>.ics` resources, the UID doubles as the event id.
+#[derive(Debug, Clone)]
+pub struct CalDavClient {
+    client: Client,
+    calendar_url: Url,
+    username: String,
+    password: String,
+}
+
+impl CalDavClient {
+    pub fn new(calendar_url: &Url, username: String, password: String) -> AnyResult<Self> {
+        Ok(CalDavClient {
+            client: Client::builder().build()?,
+            calendar_url: collection_url(calendar_url),
+            username,
+            password,
+        })
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        self.client
+            .request(method, url)
+            .basic_auth(&self.username, Some(&self.password))
+    }
+
+    fn event_url(&self, event_id: &str) -> AnyResult<Url> {
+        Ok(self.calendar_url.join(&format!("{event_id}.ics"))?)
+    }
+
+    async fn report(&self, start: UtcDateTime, end: UtcDateTime) -> AnyResult<Vec<String>> {
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="{CALDAV_NAMESPACE}">
+  <d:prop><d:getetag/><c:calendar-data/></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT">
+        <c:time-range start="{}" end="{}"/>
+      </c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#,
+            start.format(DATE_TIME_FORMAT),
+            end.format(DATE_TIME_FORMAT)
+        );
+        let response = self
+            .request(Method::from_bytes(b"REPORT")?, self.calendar_url.clone())
+            .header("Depth", "1")
+            .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        calendar_data(&response)
+    }
+
+    /// The event and its ETag.
+    async fn get(&self, event_id: &str) -> AnyResult<(VEvent, Option<String>)> {
+        let response = self
+            .request(Method::GET, self.event_url(event_id)?)
+            .send()
+            .await?
+            .error_for_status()?;
+        let etag = etag(&response);
+        let ics = response.text().await?;
+        let event = VEvent::parse(&ics).ok_or_else(|| eyre!("No event in {event_id}.ics"))?;
+        Ok((event, etag))
+    }
+
+    /// Writes the event, `If-Match` with the ETag guards against overwriting concurrent
+    /// edits, `If-None-Match: *` against overwriting an existing event.
+    async fn put(&self, event: &VEvent, etag: Option<&str>, create: bool) -> AnyResult<String> {
+        let ics = event.to_ics();
+        self.put_ics(&event.uid, &ics, etag, create).await?;
+        Ok(ics)
+    }
+
+    async fn put_ics(
+        &self,
+        event_id: &str,
+        ics: &str,
+        etag: Option<&str>,
+        create: bool,
+    ) -> AnyResult<()> {
+        let mut request = self
+            .request(Method::PUT, self.event_url(event_id)?)
+            .header(header::CONTENT_TYPE, "text/calendar; charset=utf-8");
+        if let Some(etag) = etag {
+            request = request.header(header::IF_MATCH, etag);
+        }
+        if create {
+            request = request.header(header::IF_NONE_MATCH, "*");
+        }
+        request
+            .body(ics.to_owned())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn insert(&self, post: &GoogleEventPost) -> AnyResult<GoogleEvent> {
+        let event = VEvent::from_post(post);
+        let ics = self.put(&event, None, true).await?;
+        Ok(event.to_google_event(&ics))
+    }
+
+    async fn patch(&self, event_id: &str, patch: &GoogleEventPatch) -> AnyResult<GoogleEvent> {
+        let (mut event, etag) = self.get(event_id).await?;
+        event.apply(patch);
+        let ics = self.put(&event, etag.as_deref(), false).await?;
+        Ok(event.to_google_event(&ics))
+    }
+}
+
+/// Calendar objects of a `REPORT` multistatus response.
+fn calendar_data(response: &str) -> AnyResult<Vec<String>> {
+    let document = roxmltree::Document::parse(response)?;
+    Ok(document
+        .descendants()
+        .filter(|x| x.has_tag_name((CALDAV_NAMESPACE, "calendar-data")))
+        .filter_map(|x| x.text())
+        .map(ToOwned::to_owned)
+        .collect())
+}
+
+/// Joining event names needs the collection url to end with a slash.
+fn collection_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    url
+}
+
+fn etag(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get(header::ETAG)
+        .and_then(|x| x.to_str().ok())
+        .map(ToOwned::to_owned)
+}
+
+#[async_trait]
+impl CalendarBackend for CalDavClient {
+    fn name(&self) -> &'static str {
+        "CalDAV"
+    }
+
+    fn calendar_id(&self) -> &str {
+        self.calendar_url.as_str()
+    }
+
+    /// Calendar ids are collection urls, relative ones are resolved against the current calendar.
+    fn with_calendar(&self, calendar_id: &str) -> AnyResult<Box<dyn CalendarBackend>> {
+        Ok(Box::new(CalDavClient {
+            calendar_url: collection_url(&self.calendar_url.join(calendar_id)?),
+            ..self.clone()
+        }))
+    }
+
+    /// Deleted events are gone on CalDAV servers, `show_deleted` has no effect.
+    async fn list_managed_events(
+        &self,
+        start: UtcDateTime,
+        end: UtcDateTime,
+        _show_deleted: bool,
+    ) -> AnyResult<Vec<GoogleEvent>> {
+        Ok(self
+            .report(start, end)
+            .await?
+            .into_iter()
+            .filter_map(|ics| Some((VEvent::parse(&ics)?, ics)))
+            .filter(|(event, _)| {
+                event.properties.get(MANAGED_PROPERTY).map(String::as_str) == Some(MANAGED_VALUE)
+            })
+            .map(|(event, ics)| event.to_google_event(&ics))
+            .collect())
+    }
+
+    async fn execute_batch(&self, batch: &GoogleEventBatch) -> Vec<ChangeResult> {
+        let mut results = Vec::with_capacity(batch.len());
+        for request in batch.requests() {
+            results.push(match request {
+                GoogleBatchRequest::Insert(post) => self.insert(post).await.map(Some),
+                GoogleBatchRequest::Patch { event_id, event } => {
+                    self.patch(event_id, event).await.map(Some)
+                }
+                GoogleBatchRequest::Delete { event_id } => {
+                    self.delete_event(event_id).await.map(|()| None)
+                }
+            });
+        }
+        results
+    }
+
+    async fn delete_event(&self, event_id: &str) -> AnyResult<()> {
+        let response = self
+            .request(Method::DELETE, self.event_url(event_id)?)
+            .send()
+            .await?;
+        if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) {
+            return Ok(());
+        }
+        response.error_for_status()?;
+        Ok(())
+    }
+
+    async fn restore_event(&self, snapshot: &GoogleEventSnapshot) -> AnyResult<GoogleEvent> {
+        let ics = snapshot
+            .json()
+            .get("ics")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| eyre!("Not a CalDAV snapshot"))?;
+        let event = VEvent::parse(ics).ok_or_else(|| eyre!("No event in the snapshot"))?;
+        // the event is put back as it was stored, with properties the utility does not know
+        self.put_ics(&event.uid, ics, None, false).await?;
+        Ok(event.to_google_event(ics))
+    }
+
+    async fn event_colors(&self) -> AnyResult<Option<Vec<String>>> {
+        Ok(None)
+    }
+}
+
+/// The parts of a `VEVENT` the utility manages.
+#[derive(Debug, Clone, Default)]
+struct VEvent {
+    uid: String,
+    summary: Option<String>,
+    description: Option<String>,
+    location: Option<String>,
+    url: Option<String>,
+    start: Option<GoogleEventTime>,
+    end: Option<GoogleEventTime>,
+    last_modified: Option<UtcDateTime>,
+    properties: HashMap<String, String>,
+    color_id: Option<String>,
+    reminders: Vec<GoogleReminder>,
+    transparent: bool,
+}
+
+impl VEvent {
+    fn from_post(post: &GoogleEventPost) -> Self {
+        VEvent {
+            uid: Uuid::new_v4().to_string(),
+            summary: Some(post.summary.clone()),
+            description: post.description.clone(),
+            location: post.location.clone(),
+            url: post.source.as_ref().map(|x| x.url.clone()),
+            start: Some(post.start),
+            end: Some(post.end),
+            last_modified: None,
+            properties: post.private_properties.clone(),
+            color_id: post.color_id.clone(),
+            reminders: post.reminders.clone().unwrap_or_default(),
+            transparent: false,
+        }
+    }
+
+    /// Empty strings clear text fields, as they do in Google Calendar.
+    fn apply(&mut self, patch: &GoogleEventPatch) {
+        let text = |value: &Option<String>, field: &mut Option<String>| {
+            if let Some(value) = value {
+                *field = Some(value.clone()).filter(|x| !x.is_empty());
+            }
+        };
+        text(&patch.summary, &mut self.summary);
+        text(&patch.description, &mut self.description);
+        text(&patch.location, &mut self.location);
+        text(&patch.color_id, &mut self.color_id);
+        if let Some(source) = &patch.source {
+            self.url = Some(source.url.clone());
+        }
+        if patch.start.is_some() {
+            self.start = patch.start;
+        }
+        if patch.end.is_some() {
+            self.end = patch.end;
+        }
+        if let Some(properties) = &patch.private_properties {
+            self.properties.extend(properties.clone());
+        }
+        if let Some(reminders) = &patch.reminders {
+            self.reminders.clone_from(reminders);
+        }
+        if let Some(transparent) = patch.transparent {
+            self.transparent = transparent;
+        }
+    }
+
+    fn to_google_event(&self, ics: &str) -> GoogleEvent {
+        GoogleEvent {
+            id: self.uid.clone(),
+            summary: self.summary.clone(),
+            description: self.description.clone(),
+            location: self.location.clone(),
+            source_url: self.url.clone(),
+            start: self.start,
+            end: self.end,
+            creator_email: None,
+            cancelled: false,
+            updated: self.last_modified,
+            private_properties: self.properties.clone(),
+            color_id: self.color_id.clone(),
+            reminders: Some(self.reminders.clone()),
+            recurrence: None,
+            recurring_event_id: None,
+            original_start: None,
+            snapshot: json!({ "id": self.uid, "ics": ics }).into(),
+        }
+    }
+
+    fn to_ics(&self) -> String {
+        let now = Utc::now().format(DATE_TIME_FORMAT);
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_owned(),
+            "VERSION:2.0".to_owned(),
+            "PRODID:-//google-calendar-utility//EN".to_owned(),
+            "BEGIN:VEVENT".to_owned(),
+            format!("UID:{}", self.uid),
+            format!("DTSTAMP:{now}"),
+            format!("LAST-MODIFIED:{now}"),
+        ];
+        lines.extend(self.start.map(|x| format_time("DTSTART", &x)));
+        lines.extend(self.end.map(|x| format_time("DTEND", &x)));
+        let texts = [
+            ("SUMMARY", &self.summary),
+            ("DESCRIPTION", &self.description),
+            ("LOCATION", &self.location),
+        ];
+        for (name, value) in texts {
+            lines.extend(value.as_ref().map(|x| format!("{name}:{}", escape(x))));
+        }
+        lines.extend(self.url.as_ref().map(|x| format!("URL:{x}")));
+        lines.extend(
+            self.color_id
+                .as_ref()
+                .map(|x| format!("{COLOR_PROPERTY}:{}", escape(x))),
+        );
+        if self.transparent {
+            lines.push("TRANSP:TRANSPARENT".to_owned());
+        }
+        let mut properties: Vec<_> = self.properties.iter().collect();
+        properties.sort();
+        for (key, value) in properties {
+            lines.push(format!("{PROPERTY};KEY=\"{key}\":{}", escape(value)));
+        }
+        for reminder in &self.reminders {
+            let action = match reminder.method {
+                ReminderMethod::Email => "EMAIL",
+                ReminderMethod::Popup => "DISPLAY",
+            };
+            lines.extend([
+                "BEGIN:VALARM".to_owned(),
+                format!("ACTION:{action}"),
+                format!("TRIGGER:-PT{}M", reminder.minutes),
+                format!(
+                    "DESCRIPTION:{}",
+                    escape(self.summary.as_deref().unwrap_or_default())
+                ),
+                format!(
+                    "SUMMARY:{}",
+                    escape(self.summary.as_deref().unwrap_or_default())
+                ),
+                "END:VALARM".to_owned(),
+            ]);
+        }
+        lines.extend(["END:VEVENT".to_owned(), "END:VCALENDAR".to_owned()]);
+        lines.iter().map(|x| fold(x)).collect()
+    }
+
+    /// Parses the first `VEVENT` of a calendar object.
+    fn parse(ics: &str) -> Option<Self> {
+        let mut event: Option<VEvent> = None;
+        let mut alarm: Option<(Option<ReminderMethod>, Option<u32>)> = None;
+        for line in unfold(ics) {
+            let Some(property) = ContentLine::parse(&line) else {
+                continue;
+            };
+            match (property.name.as_str(), property.value.as_str()) {
+                ("BEGIN", "VEVENT") if event.is_none() => event = Some(VEvent::default()),
+                ("END", "VEVENT") => break,
+                ("BEGIN", "VALARM") => alarm = Some((None, None)),
+                ("END", "VALARM") => {
+                    if let (Some(event), Some((Some(method), Some(minutes)))) =
+                        (event.as_mut(), alarm.take())
+                    {
+                        event.reminders.push(GoogleReminder { method, minutes });
+                    }
+                }
+                _ => {}
+            }
+            let Some(event) = event.as_mut() else {
+                continue;
+            };
+            if let Some((method, minutes)) = alarm.as_mut() {
+                match property.name.as_str() {
+                    "ACTION" => {
+                        *method = match property.value.as_str() {
+                            "EMAIL" => Some(ReminderMethod::Email),
+                            "DISPLAY" => Some(ReminderMethod::Popup),
+                            _ => None,
+                        };
+                    }
+                    "TRIGGER" => *minutes = parse_trigger(&property.value),
+                    _ => {}
+                }
+                continue;
+            }
+            event.set(&property);
+        }
+        event.filter(|x| !x.uid.is_empty())
+    }
+
+    fn set(&mut self, property: &ContentLine) {
+        let text = || Some(unescape(&property.value));
+        match property.name.as_str() {
+            "UID" => self.uid.clone_from(&property.value),
+            "SUMMARY" => self.summary = text(),
+            "DESCRIPTION" => self.description = text(),
+            "LOCATION" => self.location = text(),
+            "URL" => self.url = Some(property.value.clone()),
+            "DTSTART" => self.start = parse_time(property),
+            "DTEND" => self.end = parse_time(property),
+            "LAST-MODIFIED" => {
+                self.last_modified =
+                    NaiveDateTime::parse_from_str(&property.value, DATE_TIME_FORMAT)
+                        .ok()
+                        .map(|x| Utc.from_utc_datetime(&x));
+            }
+            "TRANSP" => self.transparent = property.value == "TRANSPARENT",
+            COLOR_PROPERTY => self.color_id = text(),
+            PROPERTY => {
+                if let Some(key) = property.parameter("KEY") {
+                    self.properties
+                        .insert(key.to_owned(), unescape(&property.value));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A property line split into its name, parameters and value, see RFC 5545 3.1.
+struct ContentLine {
+    name: String,
+    parameters: Vec<(String, String)>,
+    value: String,
+}
+
+impl ContentLine {
+    fn parse(line: &str) -> Option<Self> {
+        // the value starts after the first colon outside of quoted parameter values
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(index, char)| match char {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(index),
+            _ => None,
+        })?;
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+        let mut parts = split_unquoted(head, ';').into_iter();
+        let name = parts.next()?.to_uppercase();
+        let parameters = parts
+            .filter_map(|x| {
+                let (key, value) = x.split_once('=')?;
+                Some((key.to_uppercase(), value.trim_matches('"').to_owned()))
+            })
+            .collect();
+        Some(ContentLine {
+            name,
+            parameters,
+            value: value.to_owned(),
+        })
+    }
+
+    fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (index, char) in text.char_indices() {
+        if char == '"' {
+            quoted = !quoted;
+        } else if char == separator && !quoted {
+            parts.push(&text[start..index]);
+            start = index + 1;
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Joins folded lines, continuation lines start with a space or a tab.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+    lines
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            result.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+fn format_time(name: &str, time: &GoogleEventTime) -> String {
+    match time {
+        GoogleEventTime::DateTime {
+            date_time,
+            time_zone: Some(time_zone),
+        } => format!(
+            "{name};{TIME_ZONE_PARAMETER}={}:{}",
+            time_zone.name(),
+            date_time.format(DATE_TIME_FORMAT)
+        ),
+        GoogleEventTime::DateTime { date_time, .. } => {
+            format!("{name}:{}", date_time.format(DATE_TIME_FORMAT))
+        }
+        GoogleEventTime::Date(date) => format!("{name};VALUE=DATE:{}", date.format(DATE_FORMAT)),
+    }
+}
+
+/// Parses UTC, `TZID` and all-day times, floating times are taken as UTC.
+fn parse_time(property: &ContentLine) -> Option<GoogleEventTime> {
+    if property.parameter("VALUE") == Some("DATE") {
+        return NaiveDate::parse_from_str(&property.value, DATE_FORMAT)
+            .ok()
+            .map(GoogleEventTime::Date);
+    }
+    let local =
+        NaiveDateTime::parse_from_str(property.value.trim_end_matches('Z'), LOCAL_DATE_TIME_FORMAT)
+            .ok()?;
+    let time_zone = property
+        .parameter("TZID")
+        .or_else(|| property.parameter(TIME_ZONE_PARAMETER))
+        .and_then(|x| x.parse::<Tz>().ok());
+    let date_time = match (property.value.ends_with('Z'), time_zone) {
+        (false, Some(tz)) => tz
+            .from_local_datetime(&local)
+            .earliest()?
+            .with_timezone(&Utc),
+        _ => Utc.from_utc_datetime(&local),
+    };
+    Some(GoogleEventTime::DateTime {
+        date_time,
+        time_zone,
+    })
+}
+
+/// Minutes before the start of a `TRIGGER` like `-PT1H30M` or `-P1D`.
+fn parse_trigger(value: &str) -> Option<u32> {
+    let duration = value.strip_prefix("-P")?;
+    let mut minutes = 0;
+    let mut number = 0;
+    for char in duration.chars() {
+        match char {
+            '0'..='9' => number = number * 10 + char.to_digit(10)?,
+            'W' => minutes += number * 7 * 24 * 60,
+            'D' => minutes += number * 24 * 60,
+            'H' => minutes += number * 60,
+            'M' => minutes += number,
+            'T' | 'S' => {}
+            _ => return None,
+        }
+        if !char.is_ascii_digit() {
+            number = 0;
+        }
+    }
+    Some(minutes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> UtcDateTime {
+        Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(text, DATE_TIME_FORMAT).unwrap())
+    }
+
+    fn event() -> VEvent {
+        VEvent {
+            uid: "class-1".to_owned(),
+            summary: Some("Yoga, level 1; with Anna".to_owned()),
+            description: Some(format!("First line\nSecond line {}", "x".repeat(120))),
+            location: Some("Москва, Тверская 1".to_owned()),
+            url: Some("https://example.com/classes/1".to_owned()),
+            start: Some(GoogleEventTime::DateTime {
+                date_time: utc("20250101T160000Z"),
+                time_zone: Some(chrono_tz::Europe::Moscow),
+            }),
+            end: Some(GoogleEventTime::DateTime {
+                date_time: utc("20250101T173000Z"),
+                time_zone: None,
+            }),
+            last_modified: None,
+            properties: HashMap::from([
+                (MANAGED_PROPERTY.to_owned(), MANAGED_VALUE.to_owned()),
+                ("gcuHashes".to_owned(), "a:b;c,d".to_owned()),
+            ]),
+            color_id: Some("7".to_owned()),
+            reminders: vec![
+                GoogleReminder {
+                    method: ReminderMethod::Popup,
+                    minutes: 90,
+                },
+                GoogleReminder {
+                    method: ReminderMethod::Email,
+                    minutes: 1440,
+                },
+            ],
+            transparent: true,
+        }
+    }
+
+    #[test]
+    fn round_trips_events() {
+        let event = event();
+        let ics = event.to_ics();
+        assert!(ics.lines().all(|x| x.len() <= 75));
+        let parsed = VEvent::parse(&ics).unwrap();
+        assert_eq!(parsed.uid, event.uid);
+        assert_eq!(parsed.summary, event.summary);
+        assert_eq!(parsed.description, event.description);
+        assert_eq!(parsed.location, event.location);
+        assert_eq!(parsed.url, event.url);
+        assert_eq!(parsed.start, event.start);
+        assert_eq!(parsed.end, event.end);
+        assert!(parsed.last_modified.is_some());
+        assert_eq!(parsed.properties, event.properties);
+        assert_eq!(parsed.color_id, event.color_id);
+        assert_eq!(parsed.reminders, event.reminders);
+        assert!(parsed.transparent);
+    }
+
+    #[test]
+    fn parses_folded_and_escaped_lines() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Yoga\\, stretch\r\n  \\; relax\r\nDESCRIPTION:a\\nb\\\\c\r\n\td\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let event = VEvent::parse(ics).unwrap();
+        assert_eq!(event.summary.as_deref(), Some("Yoga, stretch ; relax"));
+        assert_eq!(event.description.as_deref(), Some("a\nb\\cd"));
+    }
+
+    #[test]
+    fn parses_quoted_parameters() {
+        let line = ContentLine::parse("X-GCU-PROPERTY;KEY=\"a:b;c\";x-other=1:value:with\\;colons")
+            .unwrap();
+        assert_eq!(line.name, PROPERTY);
+        assert_eq!(line.parameter("KEY"), Some("a:b;c"));
+        assert_eq!(line.parameter("X-OTHER"), Some("1"));
+        assert_eq!(line.value, "value:with\\;colons");
+    }
+
+    #[test]
+    fn parses_times() {
+        let time = |line: &str| parse_time(&ContentLine::parse(line).unwrap());
+        assert_eq!(
+            time("DTSTART;TZID=Europe/Moscow:20250101T190000"),
+            Some(GoogleEventTime::DateTime {
+                date_time: utc("20250101T160000Z"),
+                time_zone: Some(chrono_tz::Europe::Moscow),
+            })
+        );
+        assert_eq!(
+            time("DTSTART:20250101T160000Z"),
+            Some(GoogleEventTime::DateTime {
+                date_time: utc("20250101T160000Z"),
+                time_zone: None,
+            })
+        );
+        assert_eq!(
+            time("DTSTART;X-GCU-TZ=Europe/Moscow:20250101T160000Z"),
+            Some(GoogleEventTime::DateTime {
+                date_time: utc("20250101T160000Z"),
+                time_zone: Some(chrono_tz::Europe::Moscow),
+            })
+        );
+        assert_eq!(
+            time("DTSTART;VALUE=DATE:20250101"),
+            Some(GoogleEventTime::Date(
+                NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()
+            ))
+        );
+        assert_eq!(time("DTSTART:tomorrow"), None);
+    }
+
+    #[test]
+    fn parses_alarm_triggers() {
+        assert_eq!(parse_trigger("-PT1H30M"), Some(90));
+        assert_eq!(parse_trigger("-P1D"), Some(1440));
+        assert_eq!(parse_trigger("-P1W2DT3H"), Some(13_140));
+        assert_eq!(parse_trigger("-PT15M0S"), Some(15));
+        assert_eq!(parse_trigger("PT15M"), None);
+        assert_eq!(parse_trigger("-PT1X"), None);
+    }
+
+    #[test]
+    fn skips_incomplete_alarms() {
+        let ics = "BEGIN:VEVENT\nUID:1\nBEGIN:VALARM\nACTION:AUDIO\nTRIGGER:-PT5M\nEND:VALARM\nBEGIN:VALARM\nACTION:DISPLAY\nTRIGGER;RELATED=END:-PT10M\nEND:VALARM\nEND:VEVENT\n";
+        let event = VEvent::parse(ics).unwrap();
+        assert_eq!(
+            event.reminders,
+            vec![GoogleReminder {
+                method: ReminderMethod::Popup,
+                minutes: 10,
+            }]
+        );
+    }
+
+    #[test]
+    fn reads_report_responses() {
+        let response = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/calendars/user/classes/1.ics</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:getetag>"1"</d:getetag>
+        <cal:calendar-data>BEGIN:VCALENDAR&#13;
+END:VCALENDAR&#13;
+</cal:calendar-data>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/calendars/user/classes/2.ics</d:href>
+    <d:propstat>
+      <d:prop><d:getetag>"2"</d:getetag><cal:calendar-data/></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+        assert_eq!(
+            calendar_data(response).unwrap(),
+            vec!["BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n"]
+        );
+        assert!(calendar_data("<d:multistatus").is_err());
+    }
+
+    /// Runs against a real server, e.g. `radicale --storage-filesystem-folder=/tmp/radicale`
+    /// with an existing calendar collection set as `GCU__CALDAV_URL`.
+    #[tokio::test]
+    #[ignore = "needs a CalDAV server"]
+    async fn syncs_with_a_server() {
+        let env = |name: &str| std::env::var(name).unwrap_or_default();
+        let client = CalDavClient::new(
+            &Url::parse(&env("GCU__CALDAV_URL")).unwrap(),
+            env("GCU__CALDAV_USERNAME"),
+            env("GCU__CALDAV_PASSWORD"),
+        )
+        .unwrap();
+        let event = event();
+        let post = GoogleEventPost {
+            summary: event.summary.clone().unwrap(),
+            description: event.description.clone(),
+            location: event.location.clone(),
+            source: None,
+            start: event.start.unwrap(),
+            end: event.end.unwrap(),
+            private_properties: event.properties.clone(),
+            color_id: event.color_id.clone(),
+            reminders: Some(event.reminders.clone()),
+            recurrence: None,
+        };
+        let (start, end) = (utc("20250101T000000Z"), utc("20250102T000000Z"));
+
+        let batch = GoogleEventBatch::new().insert(post);
+        let created = client
+            .execute_batch(&batch)
+            .await
+            .remove(0)
+            .unwrap()
+            .unwrap();
+        let listed = client.list_managed_events(start, end, false).await.unwrap();
+        let listed = listed.iter().find(|x| x.id == created.id).unwrap();
+        assert_eq!(listed.summary, event.summary);
+        assert_eq!(listed.description, event.description);
+        assert_eq!(listed.start, event.start);
+        assert_eq!(listed.private_properties, event.properties);
+        assert_eq!(listed.reminders.as_ref(), Some(&event.reminders));
+
+        let patch = GoogleEventPatch {
+            summary: Some("Stretching".to_owned()),
+            ..GoogleEventPatch::default()
+        };
+        let batch = GoogleEventBatch::new().patch(&created.id, patch);
+        let patched = client
+            .execute_batch(&batch)
+            .await
+            .remove(0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(patched.summary.as_deref(), Some("Stretching"));
+        assert_eq!(patched.description, event.description);
+
+        client.delete_event(&created.id).await.unwrap();
+        let listed = client.list_managed_events(start, end, false).await.unwrap();
+        assert!(listed.iter().all(|x| x.id != created.id));
+        let restored = client.restore_event(&created.snapshot).await.unwrap();
+        assert_eq!(restored.id, created.id);
+        client.delete_event(&created.id).await.unwrap();
+    }
+}
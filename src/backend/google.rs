@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+use color_eyre::Result as AnyResult;
+use google_api::{
+    batch::GoogleEventBatch,
+    errors::GoogleClientError,
+    models::{GoogleEvent, GoogleEventListParams, GoogleEventSnapshot},
+    GoogleClient,
+};
+
+use super::{CalendarBackend, ChangeResult};
+use crate::api_clients::models::{UtcDateTime, MANAGED_PROPERTY, MANAGED_VALUE};
+
+#[async_trait]
+impl CalendarBackend for GoogleClient {
+    fn name(&self) -> &'static str {
+        "Google Calendar"
+    }
+
+    fn calendar_id(&self) -> &str {
+        GoogleClient::calendar_id(self)
+    }
+
+    fn with_calendar(&self, calendar_id: &str) -> AnyResult<Box<dyn CalendarBackend>> {
+        Ok(Box::new(GoogleClient::with_calendar(self, calendar_id)?))
+    }
+
+    async fn list_managed_events(
+        &self,
+        start: UtcDateTime,
+        end: UtcDateTime,
+        show_deleted: bool,
+    ) -> AnyResult<Vec<GoogleEvent>> {
+        Ok(self
+            .list_events(
+                &GoogleEventListParams::new()
+                    .start(start)
+                    .end(end)
+                    .show_deleted(show_deleted)
+                    .private_property(MANAGED_PROPERTY, MANAGED_VALUE),
+            )
+            .await?)
+    }
+
+    /// Earlier versions recognised their events by the service account being the creator.
+    async fn list_unmarked_events(
+        &self,
+        start: UtcDateTime,
+        end: UtcDateTime,
+    ) -> AnyResult<Vec<GoogleEvent>> {
+        let email = self.service_account_email();
+        Ok(self
+            .list_events(&GoogleEventListParams::new().start(start).end(end))
+            .await?
+            .into_iter()
+            .filter(|event| {
+                !event.private_properties.contains_key(MANAGED_PROPERTY)
+                    && event.creator_email.as_ref() == Some(email)
+            })
+            .collect())
+    }
+
+    async fn execute_batch(&self, batch: &GoogleEventBatch) -> Vec<ChangeResult> {
+        GoogleClient::execute_batch(self, batch)
+            .await
+            .into_iter()
+            .map(|x| x.map_err(Into::into))
+            .collect()
+    }
+
+    async fn delete_event(&self, event_id: &str) -> AnyResult<()> {
+        match GoogleClient::delete_event(self, event_id).await {
+            Ok(_) | Err(GoogleClientError::NotFound { .. } | GoogleClientError::Gone { .. }) => {
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn restore_event(&self, snapshot: &GoogleEventSnapshot) -> AnyResult<GoogleEvent> {
+        Ok(GoogleClient::restore_event(self, snapshot).await?)
+    }
+
+    async fn event_colors(&self) -> AnyResult<Option<Vec<String>>> {
+        let colors = self.get_colors().await?;
+        Ok(Some(colors.event.into_keys().collect()))
+    }
+}
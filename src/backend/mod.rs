@@ -0,0 +1,49 @@
+use async_trait::async_trait;
+use color_eyre::Result as AnyResult;
+use google_api::{
+    batch::GoogleEventBatch,
+    models::{GoogleEvent, GoogleEventSnapshot},
+};
+
+use crate::api_clients::models::UtcDateTime;
+
+pub mod caldav;
+pub mod google;
+
+/// Result of a single change: the created/updated event, or `None` for deletes.
+pub type ChangeResult = AnyResult<Option<GoogleEvent>>;
+
+/// Calendar service events are synced to. Google models are used for events
+/// on every backend, private properties mark the events managed by the utility.
+#[async_trait]
+pub trait CalendarBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Id of the calendar whose events this backend works with.
+    fn calendar_id(&self) -> &str;
+    /// Same backend working with another calendar.
+    fn with_calendar(&self, calendar_id: &str) -> AnyResult<Box<dyn CalendarBackend>>;
+    /// Managed events overlapping `start..end`, with deleted ones if the backend keeps them.
+    async fn list_managed_events(
+        &self,
+        start: UtcDateTime,
+        end: UtcDateTime,
+        show_deleted: bool,
+    ) -> AnyResult<Vec<GoogleEvent>>;
+    /// Events the utility created before it marked them as managed, empty if there can be none.
+    async fn list_unmarked_events(
+        &self,
+        _start: UtcDateTime,
+        _end: UtcDateTime,
+    ) -> AnyResult<Vec<GoogleEvent>> {
+        Ok(Vec::new())
+    }
+    /// Applies the changes in order, one result per change: a failed change,
+    /// or a failed group of changes, does not stop the rest.
+    async fn execute_batch(&self, batch: &GoogleEventBatch) -> Vec<ChangeResult>;
+    /// Deletes an event, events that are already gone are not an error.
+    async fn delete_event(&self, event_id: &str) -> AnyResult<()>;
+    /// Puts an event back as it was when the snapshot was taken.
+    async fn restore_event(&self, snapshot: &GoogleEventSnapshot) -> AnyResult<GoogleEvent>;
+    /// Valid event color ids, `None` if the backend does not know colors.
+    async fn event_colors(&self) -> AnyResult<Option<Vec<String>>>;
+}
@@ -0,0 +1,57 @@
+use color_eyre::Result as AnyResult;
+use email_address::EmailAddress;
+use google_api::{
+    models::{AclRole, AclScope, GoogleCalendarPost},
+    GoogleClient,
+};
+
+/// Finds the service account's calendar named `name`, creating it on first run,
+/// and makes sure it is shared with `share_with`. Returns the calendar id.
+pub async fn ensure_calendar(
+    google_client: &GoogleClient,
+    name: &str,
+    share_with: Option<&EmailAddress>,
+) -> AnyResult<String> {
+    let existing = google_client
+        .list_calendars()
+        .await?
+        .into_iter()
+        .find(|x| x.summary == name && x.access_role == Some(AclRole::Owner));
+    let calendar = if let Some(calendar) = existing {
+        calendar
+    } else {
+        let calendar = google_client
+            .create_calendar(&GoogleCalendarPost {
+                summary: name.to_owned(),
+                description: Some("Classes booked in studios".to_owned()),
+                time_zone: None,
+            })
+            .await?;
+        log::info!("Created calendar {} ({})", calendar.summary, calendar.id);
+        calendar
+    };
+    if let Some(email) = share_with {
+        share_calendar(google_client, &calendar.id, email).await?;
+    }
+    Ok(calendar.id)
+}
+
+async fn share_calendar(
+    google_client: &GoogleClient,
+    calendar_id: &str,
+    email: &EmailAddress,
+) -> AnyResult<()> {
+    let scope = AclScope::User(email.clone());
+    let shared = google_client
+        .list_acl(calendar_id)
+        .await?
+        .iter()
+        .any(|x| x.scope == scope && x.role >= AclRole::Writer);
+    if !shared {
+        google_client
+            .insert_acl(calendar_id, AclRole::Writer, &scope, true)
+            .await?;
+        log::info!("Shared calendar {calendar_id} with {email}");
+    }
+    Ok(())
+}
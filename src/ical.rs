@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+
+use camino::Utf8Path;
+use chrono::Utc;
+use color_eyre::Result as AnyResult;
+use convert_case::{Case, Casing};
+
+use crate::api_clients::{
+    models::{Class, UtcDateTime},
+    StudioCRUD,
+};
+use crate::models::SyncConfig;
+use crate::sync::get_all_classes;
+
+const PRODUCT_ID: &str = "-//google-calendar-utility//EN";
+const UID_DOMAIN: &str = "google-calendar-utility";
+pub const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+/// Content lines longer than this many octets are folded, see RFC 5545 3.1.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Writes all upcoming bookings to an `.ics` file.
+pub async fn export(
+    clients: &[Box<dyn StudioCRUD + Send + Sync>],
+    config: &SyncConfig,
+    concurrency: usize,
+    path: &Utf8Path,
+) -> AnyResult<()> {
+    let (classes, _) = upcoming_classes(clients, concurrency).await;
+    std::fs::write(path, calendar(&classes, config))?;
+    log::info!("Exported {} classes to {path}", classes.len());
+    Ok(())
+}
+
+/// Bookings that have not ended yet, and the studios whose bookings could not be fetched.
+pub async fn upcoming_classes(
+    clients: &[Box<dyn StudioCRUD + Send + Sync>],
+    concurrency: usize,
+) -> (Vec<Class>, HashSet<String>) {
+    let now = Utc::now();
+    let (mut classes, failed) = get_all_classes(clients, concurrency).await;
+    classes.retain(|class| class.end > now);
+    classes.sort_by_key(|class| class.start);
+    (classes, failed)
+}
+
+/// Renders bookings as an RFC 5545 calendar, events use the summary and description
+/// templates of the sync config and keep their UIDs across exports.
+pub fn calendar(classes: &[Class], config: &SyncConfig) -> String {
+    let now = Utc::now();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        format!("PRODID:{PRODUCT_ID}"),
+        "CALSCALE:GREGORIAN".to_owned(),
+    ];
+    for class in classes {
+        lines.extend(event(class, config, now));
+    }
+    lines.push("END:VCALENDAR".to_owned());
+    lines.iter().map(|x| fold(x)).collect()
+}
+
+/// Stable across exports and syncs, so calendar apps update events instead of duplicating them.
+pub fn uid(class: &Class) -> String {
+    format!(
+        "{}-{}@{UID_DOMAIN}",
+        class.studio.to_case(Case::Kebab),
+        class.id
+    )
+}
+
+fn event(class: &Class, config: &SyncConfig, now: UtcDateTime) -> Vec<String> {
+    let post = class.to_google_post(&config.studio(&class.studio));
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_owned(),
+        format!("UID:{}", uid(class)),
+        format!("DTSTAMP:{}", now.format(DATE_TIME_FORMAT)),
+        format!("DTSTART:{}", class.start.format(DATE_TIME_FORMAT)),
+        format!("DTEND:{}", class.end.format(DATE_TIME_FORMAT)),
+        format!("SUMMARY:{}", escape(&post.summary)),
+    ];
+    if let Some(description) = &post.description {
+        lines.push(format!("DESCRIPTION:{}", escape(description)));
+    }
+    if let Some(location) = &post.location {
+        lines.push(format!("LOCATION:{}", escape(location)));
+    }
+    if let Some(url) = &class.booking_url {
+        lines.push(format!("URL:{url}"));
+    }
+    lines.push("END:VEVENT".to_owned());
+    lines
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Splits a content line into CRLF terminated lines of at most 75 octets,
+/// continuation lines start with a space. Characters are never split.
+pub fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut octets = 0;
+    for char in line.chars() {
+        if octets + char.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(char);
+        octets += char.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(
+            escape("Yoga, level 1; C:\\mat\nbring water\r\n"),
+            r"Yoga\, level 1\; C:\\mat\nbring water\n"
+        );
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        assert_eq!(fold("SUMMARY:Yoga"), "SUMMARY:Yoga\r\n");
+        let line = format!("DESCRIPTION:{}", "x".repeat(100));
+        let folded = fold(&line);
+        let lines: Vec<_> = folded.split_terminator("\r\n").collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), MAX_LINE_OCTETS);
+        assert_eq!(lines[1], format!(" {}", "x".repeat(112 - MAX_LINE_OCTETS)));
+        assert_eq!(folded.replace("\r\n ", ""), format!("{line}\r\n"));
+    }
+
+    #[test]
+    fn folds_without_splitting_characters() {
+        let line = format!("SUMMARY:{}", "й".repeat(50));
+        let folded = fold(&line);
+        assert!(folded
+            .split_terminator("\r\n")
+            .all(|x| x.len() <= MAX_LINE_OCTETS));
+        assert_eq!(folded.replace("\r\n ", ""), format!("{line}\r\n"));
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use chrono::Utc;
+use color_eyre::{eyre::eyre, Result as AnyResult};
+use google_api::{batch::GoogleEventBatch, models::GoogleEventSnapshot};
+use serde::{Deserialize, Serialize};
+
+use crate::api_clients::models::UtcDateTime;
+use crate::backend::CalendarBackend;
+use crate::sync::deletion_stamp;
+
+/// Changes one sync run made to one calendar, written before they are applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub calendar_id: String,
+    pub time: UtcDateTime,
+    /// Ids of the created events, filled in once the changes are applied
+    pub created: Vec<String>,
+    pub updated: Vec<GoogleEventSnapshot>,
+    pub deleted: Vec<GoogleEventSnapshot>,
+}
+
+/// Directory of a sync run's journal entries, one file per changed calendar.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    run_id: String,
+    dir: Utf8PathBuf,
+}
+
+impl Journal {
+    /// Journal of a new run, ids sort by the start of the run.
+    pub fn new(root: &Utf8Path) -> Self {
+        let run_id = Utc::now().format("%Y%m%dT%H%M%S%3fZ").to_string();
+        Journal {
+            dir: root.join(&run_id),
+            run_id,
+        }
+    }
+
+    pub fn open(root: &Utf8Path, run_id: &str) -> AnyResult<Self> {
+        let dir = root.join(run_id);
+        if !dir.is_dir() {
+            return Err(eyre!("No journal for run {run_id} in {root}"));
+        }
+        Ok(Journal {
+            run_id: run_id.to_owned(),
+            dir,
+        })
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Whether an entry has been written, i.e. the run is about to change or has changed a calendar.
+    pub fn is_written(&self) -> bool {
+        self.dir.is_dir()
+    }
+
+    pub fn write(&self, entry: &JournalEntry) -> AnyResult<()> {
+        fs::create_dir_all(&self.dir)?;
+        let file = File::create(self.dir.join(file_name(&entry.calendar_id)))?;
+        serde_json::to_writer_pretty(file, entry)?;
+        Ok(())
+    }
+
+    pub fn entries(&self) -> AnyResult<Vec<JournalEntry>> {
+        let mut entries = Vec::new();
+        for file in self.dir.read_dir_utf8()? {
+            let path = file?.into_path();
+            if path.extension() == Some("json") {
+                entries.push(serde_json::from_reader(File::open(path)?)?);
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Calendars synced by past runs, per backend, next to the run directories.
+const CALENDARS_FILE: &str = "calendars.json";
+
+/// Calendars the backend has synced classes to before, so calendars dropped from
+/// the sync config still get their events deleted.
+pub fn synced_calendars(root: &Utf8Path, backend: &str) -> AnyResult<HashSet<String>> {
+    Ok(read_synced_calendars(root)?
+        .remove(backend)
+        .unwrap_or_default()
+        .into_iter()
+        .collect())
+}
+
+pub fn record_synced_calendars(
+    root: &Utf8Path,
+    backend: &str,
+    calendar_ids: &HashSet<String>,
+) -> AnyResult<()> {
+    let mut calendars = read_synced_calendars(root)?;
+    calendars.insert(backend.to_owned(), calendar_ids.iter().cloned().collect());
+    fs::create_dir_all(root)?;
+    serde_json::to_writer_pretty(File::create(root.join(CALENDARS_FILE))?, &calendars)?;
+    Ok(())
+}
+
+fn read_synced_calendars(root: &Utf8Path) -> AnyResult<HashMap<String, BTreeSet<String>>> {
+    let path = root.join(CALENDARS_FILE);
+    if !path.is_file() {
+        return Ok(HashMap::new());
+    }
+    Ok(serde_json::from_reader(File::open(path)?)?)
+}
+
+/// Calendars whose events from before the managed mark have been marked.
+const MIGRATED_FILE: &str = "migrated.json";
+
+pub fn migrated_calendars(root: &Utf8Path) -> AnyResult<HashSet<String>> {
+    let path = root.join(MIGRATED_FILE);
+    if !path.is_file() {
+        return Ok(HashSet::new());
+    }
+    Ok(serde_json::from_reader(File::open(path)?)?)
+}
+
+pub fn record_migrated_calendar(root: &Utf8Path, calendar_id: &str) -> AnyResult<()> {
+    let mut calendars: BTreeSet<String> = migrated_calendars(root)?.into_iter().collect();
+    calendars.insert(calendar_id.to_owned());
+    fs::create_dir_all(root)?;
+    serde_json::to_writer_pretty(File::create(root.join(MIGRATED_FILE))?, &calendars)?;
+    Ok(())
+}
+
+fn file_name(calendar_id: &str) -> String {
+    let name: String = calendar_id
+        .chars()
+        .map(|x| if x.is_ascii_alphanumeric() { x } else { '_' })
+        .collect();
+    format!("{name}.json")
+}
+
+/// Undoes a sync run: deletes the events it created and puts back
+/// the ones it updated or deleted as they were before the run.
+pub async fn rollback(backend: &dyn CalendarBackend, journal: &Journal) -> AnyResult<()> {
+    for entry in journal.entries()? {
+        let client = backend.with_calendar(&entry.calendar_id)?;
+        if !entry.created.is_empty() {
+            // marked like the sync's own deletions, so two-way sync does not cancel their bookings
+            let mut batch = GoogleEventBatch::new();
+            for event_id in &entry.created {
+                batch = batch.patch(event_id, deletion_stamp(&HashMap::new()));
+            }
+            for result in client.execute_batch(&batch).await {
+                if let Err(e) = result {
+                    log::warn!("Could not mark a created event for deletion: {e}");
+                }
+            }
+        }
+        for event_id in &entry.created {
+            match client.delete_event(event_id).await {
+                Ok(()) => log::info!("Deleted created event {event_id}"),
+                Err(e) => log::error!("Could not delete created event {event_id}: {e}"),
+            }
+        }
+        for snapshot in entry.updated.iter().chain(&entry.deleted) {
+            let event_id = snapshot.id().unwrap_or("-");
+            match client.restore_event(snapshot).await {
+                Ok(event) => log::info!("Restored {} at {}", event.summary(), event.start()),
+                Err(e) => log::error!("Could not restore event {event_id}: {e}"),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use google_api::{batch::GoogleBatchRequest, models::GoogleEvent};
+    use serde_json::json;
+
+    use super::*;
+    use crate::api_clients::models::DELETED_PROPERTY;
+    use crate::backend::ChangeResult;
+
+    fn root() -> (tempfile::TempDir, Utf8PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::from_path_buf(dir.path().join("journal")).unwrap();
+        (dir, root)
+    }
+
+    fn entry(calendar_id: &str) -> JournalEntry {
+        JournalEntry {
+            calendar_id: calendar_id.to_owned(),
+            time: Utc::now(),
+            created: vec!["created".to_owned()],
+            updated: vec![json!({ "id": "updated", "summary": "Yoga" }).into()],
+            deleted: vec![json!({ "id": "deleted", "extendedProperties": {} }).into()],
+        }
+    }
+
+    #[test]
+    fn round_trips_entries() {
+        let (_dir, root) = root();
+        let journal = Journal::new(&root);
+        assert!(!journal.is_written());
+        assert!(Journal::open(&root, journal.run_id()).is_err());
+        let mut entry = entry("me@example.com");
+        entry.created.clear();
+        journal.write(&entry).unwrap();
+        // the second write of a calendar adds the created ids
+        entry.created.push("created".to_owned());
+        journal.write(&entry).unwrap();
+        journal
+            .write(&self::entry("https://dav.example.com/work/"))
+            .unwrap();
+        assert!(journal.is_written());
+
+        let mut entries = Journal::open(&root, journal.run_id())
+            .unwrap()
+            .entries()
+            .unwrap();
+        entries.sort_by(|a, b| a.calendar_id.cmp(&b.calendar_id));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].calendar_id, entry.calendar_id);
+        assert_eq!(entries[1].time, entry.time);
+        assert_eq!(entries[1].created, entry.created);
+        assert_eq!(entries[1].updated, entry.updated);
+        assert_eq!(entries[1].deleted, entry.deleted);
+    }
+
+    #[test]
+    fn records_calendars_per_backend() {
+        let (_dir, root) = root();
+        assert!(synced_calendars(&root, "Google").unwrap().is_empty());
+        let google = HashSet::from(["a".to_owned(), "b".to_owned()]);
+        record_synced_calendars(&root, "Google", &google).unwrap();
+        record_synced_calendars(&root, "CalDAV", &HashSet::from(["c".to_owned()])).unwrap();
+        assert_eq!(synced_calendars(&root, "Google").unwrap(), google);
+        record_synced_calendars(&root, "Google", &HashSet::new()).unwrap();
+        assert!(synced_calendars(&root, "Google").unwrap().is_empty());
+        assert_eq!(synced_calendars(&root, "CalDAV").unwrap().len(), 1);
+
+        assert!(migrated_calendars(&root).unwrap().is_empty());
+        record_migrated_calendar(&root, "a").unwrap();
+        record_migrated_calendar(&root, "a").unwrap();
+        record_migrated_calendar(&root, "b").unwrap();
+        assert_eq!(migrated_calendars(&root).unwrap(), google);
+    }
+
+    /// Records the changes made to its calendars.
+    #[derive(Clone)]
+    struct FakeBackend {
+        calendar_id: String,
+        changes: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeBackend {
+        fn record(&self, change: &str, event_id: &str) {
+            self.changes
+                .lock()
+                .unwrap()
+                .push(format!("{} {change} {event_id}", self.calendar_id));
+        }
+    }
+
+    #[async_trait]
+    impl CalendarBackend for FakeBackend {
+        fn name(&self) -> &'static str {
+            "Fake"
+        }
+
+        fn calendar_id(&self) -> &str {
+            &self.calendar_id
+        }
+
+        fn with_calendar(&self, calendar_id: &str) -> AnyResult<Box<dyn CalendarBackend>> {
+            Ok(Box::new(FakeBackend {
+                calendar_id: calendar_id.to_owned(),
+                ..self.clone()
+            }))
+        }
+
+        async fn list_managed_events(
+            &self,
+            _start: UtcDateTime,
+            _end: UtcDateTime,
+            _show_deleted: bool,
+        ) -> AnyResult<Vec<GoogleEvent>> {
+            Ok(Vec::new())
+        }
+
+        async fn execute_batch(&self, batch: &GoogleEventBatch) -> Vec<ChangeResult> {
+            batch
+                .requests()
+                .iter()
+                .map(|request| {
+                    match request {
+                        GoogleBatchRequest::Patch { event_id, event } => {
+                            let stamped = event
+                                .private_properties
+                                .as_ref()
+                                .is_some_and(|x| x.contains_key(DELETED_PROPERTY));
+                            self.record(if stamped { "stamp" } else { "patch" }, event_id);
+                        }
+                        GoogleBatchRequest::Delete { event_id } => self.record("delete", event_id),
+                        GoogleBatchRequest::Insert(_) => self.record("insert", "-"),
+                    }
+                    Ok(None)
+                })
+                .collect()
+        }
+
+        async fn delete_event(&self, event_id: &str) -> AnyResult<()> {
+            self.record("delete", event_id);
+            Ok(())
+        }
+
+        async fn restore_event(&self, snapshot: &GoogleEventSnapshot) -> AnyResult<GoogleEvent> {
+            let event_id = snapshot.id().ok_or_else(|| eyre!("No id"))?;
+            self.record("restore", event_id);
+            Ok(GoogleEvent {
+                id: event_id.to_owned(),
+                summary: None,
+                description: None,
+                location: None,
+                source_url: None,
+                start: None,
+                end: None,
+                creator_email: None,
+                cancelled: false,
+                updated: None,
+                private_properties: HashMap::new(),
+                color_id: None,
+                reminders: None,
+                recurrence: None,
+                recurring_event_id: None,
+                original_start: None,
+                snapshot: snapshot.clone(),
+            })
+        }
+
+        async fn event_colors(&self) -> AnyResult<Option<Vec<String>>> {
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn rolls_back_runs() {
+        let (_dir, root) = root();
+        let journal = Journal::new(&root);
+        journal.write(&entry("work")).unwrap();
+        let backend = FakeBackend {
+            calendar_id: "default".to_owned(),
+            changes: Arc::default(),
+        };
+        rollback(&backend, &journal).await.unwrap();
+        assert_eq!(
+            *backend.changes.lock().unwrap(),
+            [
+                "work stamp created",
+                "work delete created",
+                "work restore updated",
+                "work restore deleted",
+            ]
+        );
+    }
+}
@@ -2,22 +2,33 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::module_name_repetitions)]
 mod api_clients;
+mod backend;
+mod calendar;
+mod ical;
+mod journal;
 mod models;
+mod ownership;
+mod server;
 mod settings;
+mod sign_up;
+mod sync;
 
-use chrono::{Duration, Utc};
 use clap::Parser;
 use color_eyre::Result as AnyResult;
 use dotenvy::dotenv;
-use email_address::EmailAddress;
-use models::SignUpConfig;
+use futures::{stream, StreamExt};
+use tokio::sync::Semaphore;
 
-use api_clients::{errors::ClientError, models::Class, StudioCRUD};
-use google_api::{
-    models::{GoogleEvent, GoogleEventListParams},
-    GoogleClient,
-};
+use api_clients::schedule_cache::ScheduleCache;
+use backend::CalendarBackend;
+use ical::export;
+use journal::{rollback, Journal};
+use server::serve;
 use settings::{Cli, Commands};
+use sign_up::{sign_up, sign_up_range, BusyTimes};
+use sync::sync_calendars;
+
+const PRIMARY_CALENDAR_ID: &str = "primary";
 
 #[tokio::main]
 async fn main() -> AnyResult<()> {
@@ -28,141 +39,85 @@ async fn main() -> AnyResult<()> {
     let clients = cli.clients().await?;
 
     match cli.command {
-        Commands::SignUp(config) => {
-            let config = config.parse()?;
-            for client in clients {
-                sign_up(&*client, &config).await;
-            }
+        Commands::SignUp(args) => {
+            let config = args.config.parse()?;
+            let busy = match (
+                &config.free_busy,
+                &args.google,
+                sign_up_range(&config, &clients),
+            ) {
+                (Some(free_busy), Some(credentials), Some((start, end))) => {
+                    let busy = async {
+                        let google_client = credentials.client(PRIMARY_CALENDAR_ID).await?;
+                        BusyTimes::fetch(&google_client, free_busy, start, end).await
+                    };
+                    // signing up without the check beats not signing up at all
+                    busy.await
+                        .inspect_err(|e| {
+                            log::warn!("Could not get free/busy time, not checking it: {e}");
+                        })
+                        .ok()
+                }
+                (Some(_), None, _) => {
+                    log::warn!("Google credentials are not set, not checking free/busy time");
+                    None
+                }
+                _ => None,
+            };
+            let cache = ScheduleCache::new();
+            // one limit shared by all studios, so it caps the requests in flight
+            let requests = Semaphore::new(cli.concurrency);
+            stream::iter(&clients)
+                .for_each_concurrent(None, |client| {
+                    sign_up(&**client, &config, &cache, busy.as_ref(), &requests)
+                })
+                .await;
         }
-        Commands::SyncCalendars(google_args) => {
-            let mut google_client = google_args.client().await?;
-            sync_google_calendar(&mut google_client, google_args.sa_email, &clients).await?;
+        Commands::SyncCalendars(args) => {
+            let backend = args.google.sync_backend().await?;
+            let config = args.google.sync_config()?;
+            sync_calendars(
+                &*backend,
+                &clients,
+                &config,
+                cli.concurrency,
+                None,
+                &args.google.journal_dir,
+                args.force,
+            )
+            .await?;
         }
-    }
-    Ok(())
-}
-
-async fn sign_up<T>(client: &T, config: &SignUpConfig)
-where
-    T: StudioCRUD + ?Sized,
-{
-    let classes = config.classes(&client.name());
-    log::debug!("Classes in config: {:?}", classes);
-
-    for potential_class in classes {
-        match client.list_day_classes(&potential_class.start).await {
-            Ok(classes) => {
-                if let Some(class) = classes.iter().find(|x| potential_class.eq(x)) {
-                    if let Err(e) = client.sign_up_for_class(class).await {
-                        if let ClientError::AlreadyExists { id: _ } = e {
-                            log::info!("Already signed up for {} class {}", client.name(), class);
-                        } else {
-                            log::error!(
-                                "Could not sign up for {} class {}: {}",
-                                client.name(),
-                                class,
-                                e
-                            );
-                        }
-                    }
-                } else {
-                    log::error!("Could not find {} class {}", client.name(), potential_class);
-                }
-            }
-            Err(err) => {
-                log::error!(
-                    "Could not get {} classes for {}: {}",
-                    client.name(),
-                    potential_class.start,
-                    err
-                );
-            }
+        Commands::Serve(args) => {
+            // Google calendars are watched for changes, so its client is kept apart from the backend
+            let google_client = match (&args.google.caldav, &args.google.credentials) {
+                (None, Some(_)) => Some(args.google.sync_client().await?),
+                _ => None,
+            };
+            let backend: Option<Box<dyn CalendarBackend>> = match &google_client {
+                Some(google_client) => Some(Box::new(google_client.clone())),
+                None if args.google.caldav.is_some() => Some(args.google.sync_backend().await?),
+                None => None,
+            };
+            let config = args.google.sync_config()?;
+            serve(
+                backend,
+                google_client,
+                clients,
+                config,
+                cli.concurrency,
+                args,
+            )
+            .await?;
         }
-    }
-}
-
-async fn sync_google_calendar(
-    google_client: &mut GoogleClient,
-    creator_email: EmailAddress,
-    clients: &Vec<Box<dyn StudioCRUD + Send + Sync>>,
-) -> AnyResult<()> {
-    let now = Utc::now();
-    let google_classes = google_client
-        .list_events(&GoogleEventListParams {
-            search_param: None,
-            start: Some(now),
-            end: Some(now + Duration::weeks(3)),
-            creator_email: Some(creator_email),
-        })
-        .await?;
-    let classes = get_all_classes(clients).await;
-    let (to_delete, to_add) = get_class_status(&google_classes, classes);
-
-    for class in to_add {
-        if let Ok(response) = google_client.create_event(&class.to_google_post()).await {
-            log::info!(
-                "Added {} at {} to calendar",
-                response.summary.unwrap(),
-                response.start.unwrap()
-            );
-        } else {
-            log::error!(
-                "Could not create Google event {} at {}",
-                class.name,
-                class.start
-            );
+        Commands::Export(args) => {
+            let config = args.config.parse()?;
+            export(&clients, &config, cli.concurrency, &args.output).await?;
         }
-    }
-    for event in to_delete {
-        if let Err(e) = google_client.delete_event(&event.id).await {
-            log::error!(
-                "Could not delete Google event {} at {}: {}",
-                event.summary(),
-                event.start(),
-                e
-            );
-        } else {
-            log::info!(
-                "Deleted Google event {} at {}",
-                event.summary(),
-                event.start(),
-            );
+        Commands::Rollback(args) => {
+            let backend = args.google.backend().await?;
+            let journal = Journal::open(&args.google.journal_dir, &args.run_id)?;
+            rollback(&*backend, &journal).await?;
         }
     }
     Ok(())
 }
-
-async fn get_all_classes(clients: &Vec<Box<dyn StudioCRUD + Send + Sync>>) -> Vec<Class> {
-    let mut classes = Vec::new();
-    for client in clients {
-        if let Ok(mut value) = client.get_user_classes().await {
-            if value.is_empty() {
-                log::info!("Nothing to do for {}", client.name());
-                continue;
-            }
-            classes.append(&mut value);
-        } else {
-            log::error!("Could not get {} user classes", client.name(),);
-        }
-    }
-    return classes;
-}
-
-/// Seperate classes into sets of (google_classes-{studio_classes}, studio_classes-{google_classes})
-/// google_classes-{studio_classes} are the classes that need to be deleted from Google Calendar
-/// studio_classes-{google_classes} are the classes that need to be added to Google Calendar
-fn get_class_status(
-    google_classes: &Vec<GoogleEvent>,
-    studio_classes: Vec<Class>,
-) -> (Vec<GoogleEvent>, Vec<Class>) {
-    let to_delete: Vec<GoogleEvent> = google_classes
-        .iter()
-        .filter(|&google| !studio_classes.iter().any(|studio| studio == google))
-        .cloned()
-        .collect();
-    let to_add = studio_classes
-        .into_iter()
-        .filter(|studio| !google_classes.iter().any(|google| studio == google))
-        .collect();
-    return (to_delete, to_add);
-}
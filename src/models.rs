@@ -3,6 +3,7 @@ use std::{collections::HashMap, fmt::Display};
 use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone, Utc, Weekday};
 use chrono_tz::Tz;
 use convert_case::{Case, Casing};
+use google_api::models::GoogleReminder;
 use serde::Deserialize;
 
 use crate::api_clients::models::Class;
@@ -18,12 +19,257 @@ pub struct SignUpConfig {
     pub offset_weeks: u8, // sign up to classes that will be in <offset_weeks> weeks
     #[serde(default)]
     classes: HashMap<String, Vec<WeeklyClass>>, // map of <Studio, List of Classes>
+    pub free_busy: Option<FreeBusyConfig>,
+}
+
+/// Calendars to check for busy time before signing up.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreeBusyConfig {
+    pub calendars: Vec<String>,
+    #[serde(default)]
+    pub on_conflict: ConflictAction,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictAction {
+    #[default]
+    Skip,
+    Warn,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConfig {
+    #[serde(default = "default_tz")]
+    timezone: Tz,
+    #[serde(default)]
+    studios: HashMap<String, StudioSyncConfig>, // map of <Studio, Event settings>
+    pub two_way: Option<TwoWayConfig>,
+    #[serde(default)]
+    pub on_studio_cancel: StudioCancelAction,
+    #[serde(default)]
+    pub window: SyncWindow,
+    #[serde(default)]
+    pub limits: SafetyLimits,
+}
+
+/// Limits on deletions and booking cancellations a sync may make to a calendar without `--force`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+#[allow(clippy::struct_field_names)] // the fields are the config keys
+pub struct SafetyLimits {
+    pub max_deletions: usize,
+    /// Share of the managed events in the sync window
+    pub max_deleted_percent: u8,
+    /// Bookings cancelled in two-way mode because their events were deleted
+    pub max_cancellations: usize,
+}
+
+/// Days around now that are synced, bookings and events outside of it are left alone.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncWindow {
+    pub past_days: u16,
+    pub future_days: u16,
+    /// Keep events of past classes as an attendance record instead of deleting
+    /// them once the studio stops listing the booking
+    pub keep_past: bool,
+}
+
+/// What happens to the event of a booking the studio has cancelled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StudioCancelAction {
+    /// Prefix the title and show the event as free time
+    #[default]
+    Mark,
+    Delete,
+}
+
+/// Cancels studio bookings whose events were deleted from Google Calendar.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoWayConfig {
+    /// Minutes to wait after the deletion before cancelling, restoring the event in time keeps the booking
+    #[serde(default = "default_grace_period")]
+    grace_period: u16,
+}
+
+/// Event settings for one studio.
+/// Templates may use `{name}`, `{instructor}`, `{studio}`, `{duration}`, `{id}`,
+/// `{location}`, `{room}` and `{bookingUrl}` placeholders.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StudioSyncConfig {
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    /// Google event color id, see `GET /colors`
+    color: Option<String>,
+    reminders: Option<Vec<GoogleReminder>>,
+    /// Google calendar id the studio's events go to instead of the default one
+    calendar: Option<String>,
+    /// Time zone events are shown in, defaults to the config-wide one
+    pub timezone: Option<Tz>,
+    pub travel: Option<TravelConfig>,
+    /// Hours before the start after which bookings can not be cancelled, defaults to the studio rules
+    cancellation_deadline: Option<u16>,
+    #[serde(default)]
+    classes: HashMap<String, ClassSyncConfig>, // map of <Class name, Event settings>
+}
+
+/// Minutes blocked in the calendar for getting to and from the studio.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TravelConfig {
+    #[serde(default)]
+    pub before: u16,
+    #[serde(default)]
+    pub after: u16,
+}
+
+/// Event settings overriding the studio ones for a single class.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassSyncConfig {
+    color: Option<String>,
+    reminders: Option<Vec<GoogleReminder>>,
+    calendar: Option<String>,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        SyncConfig {
+            timezone: default_tz(),
+            studios: HashMap::new(),
+            two_way: None,
+            on_studio_cancel: StudioCancelAction::default(),
+            window: SyncWindow::default(),
+            limits: SafetyLimits::default(),
+        }
+    }
+}
+
+impl Default for SafetyLimits {
+    fn default() -> Self {
+        SafetyLimits {
+            max_deletions: 10,
+            max_deleted_percent: 50,
+            max_cancellations: 3,
+        }
+    }
+}
+
+impl Default for SyncWindow {
+    fn default() -> Self {
+        SyncWindow {
+            past_days: 0,
+            future_days: 21,
+            keep_past: false,
+        }
+    }
+}
+
+impl SyncWindow {
+    pub fn start(&self, now: UtcDateTime) -> UtcDateTime {
+        now - Duration::days(self.past_days.into())
+    }
+
+    pub fn end(&self, now: UtcDateTime) -> UtcDateTime {
+        now + Duration::days(self.future_days.into())
+    }
+
+    /// Whether `start..end` overlaps the window, the rule Google applies when listing events.
+    pub fn overlaps(&self, now: UtcDateTime, start: UtcDateTime, end: UtcDateTime) -> bool {
+        self.start(now) < end && start < self.end(now)
+    }
+}
+
+impl SyncConfig {
+    pub fn studio(&self, studio: &str) -> StudioSyncConfig {
+        let mut config = self
+            .studios
+            .get(&studio.to_case(Case::Camel))
+            .cloned()
+            .unwrap_or_default();
+        config.timezone.get_or_insert(self.timezone);
+        config
+    }
+
+    /// All configured color ids.
+    pub fn colors(&self) -> Vec<&String> {
+        self.studios
+            .values()
+            .flat_map(|studio| {
+                studio
+                    .color
+                    .iter()
+                    .chain(studio.classes.values().filter_map(|x| x.color.as_ref()))
+            })
+            .collect()
+    }
+
+    /// All calendar ids events are routed to besides the default one.
+    pub fn calendars(&self) -> Vec<&String> {
+        self.studios
+            .values()
+            .flat_map(|studio| {
+                studio
+                    .calendar
+                    .iter()
+                    .chain(studio.classes.values().filter_map(|x| x.calendar.as_ref()))
+            })
+            .collect()
+    }
+}
+
+impl StudioSyncConfig {
+    fn class(&self, class_name: &str) -> Option<&ClassSyncConfig> {
+        self.classes
+            .iter()
+            .find(|(name, _)| name.to_uppercase() == class_name.to_uppercase())
+            .map(|(_, config)| config)
+    }
+
+    pub fn color(&self, class_name: &str) -> Option<String> {
+        self.class(class_name)
+            .and_then(|x| x.color.clone())
+            .or_else(|| self.color.clone())
+    }
+
+    pub fn reminders(&self, class_name: &str) -> Option<Vec<GoogleReminder>> {
+        self.class(class_name)
+            .and_then(|x| x.reminders.clone())
+            .or_else(|| self.reminders.clone())
+    }
+
+    pub fn calendar(&self, class_name: &str) -> Option<String> {
+        self.class(class_name)
+            .and_then(|x| x.calendar.clone())
+            .or_else(|| self.calendar.clone())
+    }
+
+    pub fn cancellation_deadline(&self) -> Option<Duration> {
+        self.cancellation_deadline
+            .map(|x| Duration::hours(x.into()))
+    }
+}
+
+impl TwoWayConfig {
+    pub fn grace_period(&self) -> Duration {
+        Duration::minutes(self.grace_period.into())
+    }
 }
 
 fn default_tz() -> Tz {
     Tz::Europe__Moscow
 }
 
+fn default_grace_period() -> u16 {
+    30
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WeeklyClass {
@@ -80,8 +326,31 @@ impl SignUpConfig {
                         start: classdatetime,
                     }
                 })
-                .collect()
+                .collect();
         }
-        return result;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlaps_the_window() {
+        let window = SyncWindow {
+            past_days: 1,
+            future_days: 7,
+            keep_past: false,
+        };
+        let now = Utc::now();
+        let (start, end) = (window.start(now), window.end(now));
+        let hour = Duration::hours(1);
+        assert!(window.overlaps(now, start - hour, start + hour));
+        assert!(window.overlaps(now, end - hour, end + hour));
+        assert!(window.overlaps(now, start - hour, end + hour));
+        // touching an edge is not overlapping
+        assert!(!window.overlaps(now, start - hour, start));
+        assert!(!window.overlaps(now, end, end + hour));
     }
 }
@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use google_api::models::{
+    GoogleEvent, GoogleEventPatch, GoogleEventPost, GoogleReminder, ReminderMethod,
+};
+
+use crate::api_clients::models::HASHES_PROPERTY;
+
+/// Event fields rendered from the booking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Summary,
+    Description,
+    Location,
+    Source,
+    Start,
+    End,
+    Color,
+    Reminders,
+}
+
+impl Field {
+    const ALL: [Field; 8] = [
+        Field::Summary,
+        Field::Description,
+        Field::Location,
+        Field::Source,
+        Field::Start,
+        Field::End,
+        Field::Color,
+        Field::Reminders,
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Field::Summary => "summary",
+            Field::Description => "description",
+            Field::Location => "location",
+            Field::Source => "source",
+            Field::Start => "start",
+            Field::End => "end",
+            Field::Color => "color",
+            Field::Reminders => "reminders",
+        }
+    }
+
+    /// Hash of the value the utility renders, `None` if the field is left to the user.
+    fn rendered(self, post: &GoogleEventPost) -> Option<u64> {
+        let value = match self {
+            Field::Summary => post.summary.clone(),
+            Field::Description => post.description.clone().unwrap_or_default(),
+            Field::Location => post.location.clone().unwrap_or_default(),
+            Field::Source => post.source.as_ref()?.url.clone(),
+            Field::Start => post.start.to_string(),
+            Field::End => post.end.to_string(),
+            Field::Color => post.color_id.clone()?,
+            Field::Reminders => reminders(post.reminders.as_ref()?),
+        };
+        Some(hash(&value))
+    }
+
+    /// Hash of the value currently stored in Google Calendar.
+    fn current(self, event: &GoogleEvent) -> u64 {
+        let value = match self {
+            Field::Summary => event.summary.clone().unwrap_or_default(),
+            Field::Description => event.description.clone().unwrap_or_default(),
+            Field::Location => event.location.clone().unwrap_or_default(),
+            Field::Source => event.source_url.clone().unwrap_or_default(),
+            Field::Start => display(event.start.as_ref()),
+            Field::End => display(event.end.as_ref()),
+            Field::Color => event.color_id.clone().unwrap_or_default(),
+            Field::Reminders => event
+                .reminders
+                .as_ref()
+                .map(|x| reminders(x))
+                .unwrap_or_default(),
+        };
+        hash(&value)
+    }
+
+    fn patch(self, patch: &mut GoogleEventPatch, post: &GoogleEventPost) {
+        match self {
+            Field::Summary => patch.summary = Some(post.summary.clone()),
+            Field::Description => {
+                patch.description = Some(post.description.clone().unwrap_or_default());
+            }
+            Field::Location => patch.location = Some(post.location.clone().unwrap_or_default()),
+            Field::Source => patch.source.clone_from(&post.source),
+            Field::Start => patch.start = Some(post.start),
+            Field::End => patch.end = Some(post.end),
+            Field::Color => patch.color_id.clone_from(&post.color_id),
+            Field::Reminders => patch.reminders.clone_from(&post.reminders),
+        }
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Changes needed to bring an event in line with its booking.
+pub struct Reconciliation {
+    pub patch: Option<GoogleEventPatch>,
+    /// Fields edited in Google Calendar that the studio has changed since, the edits are kept.
+    pub conflicts: Vec<Field>,
+}
+
+/// Stores hashes of the rendered fields in the post so later syncs can tell user edits apart.
+pub fn signed(mut post: GoogleEventPost) -> GoogleEventPost {
+    let hashes = Field::ALL
+        .iter()
+        .filter_map(|field| Some((*field, field.rendered(&post)?)))
+        .collect::<Vec<_>>();
+    post.private_properties
+        .insert(HASHES_PROPERTY.to_owned(), format_hashes(&hashes));
+    post
+}
+
+/// Patches the fields of `event` that differ from the rendered `post`, unless they were
+/// edited in Google Calendar: a field is edited if its value no longer matches the hash
+/// stored when the utility last wrote it. Events without stored hashes are fully owned.
+pub fn reconcile(event: &GoogleEvent, post: &GoogleEventPost) -> Reconciliation {
+    let stored = event
+        .private_properties
+        .get(HASHES_PROPERTY)
+        .map(|x| parse_hashes(x))
+        .unwrap_or_default();
+    let mut patch = GoogleEventPatch::default();
+    let mut changed = false;
+    let mut conflicts = Vec::new();
+    let mut hashes = Vec::new();
+    for field in Field::ALL {
+        let Some(rendered) = field.rendered(post) else {
+            continue;
+        };
+        let current = field.current(event);
+        match stored.get(field.as_str()) {
+            _ if current == rendered => {}
+            Some(last) if *last != current => {
+                if *last != rendered {
+                    conflicts.push(field);
+                }
+            }
+            _ => {
+                field.patch(&mut patch, post);
+                changed = true;
+            }
+        }
+        // the rendered value is remembered even for edited fields, so a conflict is reported once
+        hashes.push((field, rendered));
+    }
+
+    let mut private_properties = post.private_properties.clone();
+    private_properties.insert(HASHES_PROPERTY.to_owned(), format_hashes(&hashes));
+    if changed
+        || private_properties
+            .iter()
+            .any(|(key, value)| event.private_properties.get(key) != Some(value))
+    {
+        patch.private_properties = Some(private_properties);
+        changed = true;
+    }
+    Reconciliation {
+        patch: changed.then_some(patch),
+        conflicts,
+    }
+}
+
+/// FNV-1a, unlike `DefaultHasher` it is stable across Rust releases.
+fn hash(value: &str) -> u64 {
+    value.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn display<T: Display>(value: Option<&T>) -> String {
+    value.map(ToString::to_string).unwrap_or_default()
+}
+
+fn reminders(reminders: &[GoogleReminder]) -> String {
+    let mut reminders = reminders.to_vec();
+    reminders.sort();
+    reminders
+        .iter()
+        .map(|x| {
+            let method = match x.method {
+                ReminderMethod::Email => "email",
+                ReminderMethod::Popup => "popup",
+            };
+            format!("{method}:{}", x.minutes)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn format_hashes(hashes: &[(Field, u64)]) -> String {
+    hashes
+        .iter()
+        .map(|(field, hash)| format!("{field}={hash:016x}"))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn parse_hashes(value: &str) -> HashMap<&str, u64> {
+    value
+        .split(';')
+        .filter_map(|x| {
+            let (field, hash) = x.split_once('=')?;
+            Some((field, u64::from_str_radix(hash, 16).ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use google_api::models::{GoogleEventSnapshot, GoogleEventTime};
+
+    use super::*;
+
+    fn post() -> GoogleEventPost {
+        let start = Utc.with_ymd_and_hms(2025, 1, 6, 16, 0, 0).unwrap();
+        GoogleEventPost {
+            summary: "Yoga".to_owned(),
+            description: Some("Studio: Plastilin".to_owned()),
+            location: None,
+            source: None,
+            start: GoogleEventTime::DateTime {
+                date_time: start,
+                time_zone: None,
+            },
+            end: GoogleEventTime::DateTime {
+                date_time: start + chrono::Duration::hours(1),
+                time_zone: None,
+            },
+            private_properties: HashMap::new(),
+            color_id: Some("7".to_owned()),
+            reminders: None,
+            recurrence: None,
+        }
+    }
+
+    /// The event as the utility last wrote it.
+    fn written(post: &GoogleEventPost) -> GoogleEvent {
+        let post = signed(post.clone());
+        GoogleEvent {
+            id: "event".to_owned(),
+            summary: Some(post.summary),
+            description: post.description,
+            location: post.location,
+            source_url: None,
+            start: Some(post.start),
+            end: Some(post.end),
+            creator_email: None,
+            cancelled: false,
+            updated: None,
+            private_properties: post.private_properties,
+            color_id: post.color_id,
+            reminders: None,
+            recurrence: None,
+            recurring_event_id: None,
+            original_start: None,
+            snapshot: GoogleEventSnapshot::default(),
+        }
+    }
+
+    #[test]
+    fn leaves_unchanged_events_alone() {
+        let reconciliation = reconcile(&written(&post()), &post());
+        assert!(reconciliation.patch.is_none());
+        assert!(reconciliation.conflicts.is_empty());
+    }
+
+    #[test]
+    fn patches_untouched_fields() {
+        let event = written(&post());
+        let mut changed = post();
+        changed.summary = "Stretching".to_owned();
+        let reconciliation = reconcile(&event, &changed);
+        let patch = reconciliation.patch.unwrap();
+        assert_eq!(patch.summary.as_deref(), Some("Stretching"));
+        assert_eq!(patch.description, None);
+        assert_eq!(
+            patch.private_properties.unwrap()[HASHES_PROPERTY],
+            signed(changed).private_properties[HASHES_PROPERTY]
+        );
+        assert!(reconciliation.conflicts.is_empty());
+    }
+
+    #[test]
+    fn keeps_edited_fields() {
+        let mut event = written(&post());
+        event.description = Some("Bring a mat".to_owned());
+        let reconciliation = reconcile(&event, &post());
+        assert!(reconciliation.patch.is_none());
+        assert!(reconciliation.conflicts.is_empty());
+
+        let mut changed = post();
+        changed.description = Some("Studio: Plastilin, room 2".to_owned());
+        changed.color_id = Some("2".to_owned());
+        let reconciliation = reconcile(&event, &changed);
+        let patch = reconciliation.patch.unwrap();
+        assert_eq!(patch.description, None);
+        assert_eq!(patch.color_id.as_deref(), Some("2"));
+        assert_eq!(reconciliation.conflicts, vec![Field::Description]);
+
+        // the next run knows the studio's value and does not report the conflict again
+        event.color_id = patch.color_id;
+        event.private_properties = patch.private_properties.unwrap();
+        let reconciliation = reconcile(&event, &changed);
+        assert!(reconciliation.patch.is_none());
+        assert!(reconciliation.conflicts.is_empty());
+    }
+
+    #[test]
+    fn owns_events_without_valid_hashes() {
+        for hashes in [None, Some("summary=zz;garbage;=1"), Some("")] {
+            let mut event = written(&post());
+            event.summary = Some("Edited".to_owned());
+            match hashes {
+                Some(hashes) => event
+                    .private_properties
+                    .insert(HASHES_PROPERTY.to_owned(), hashes.to_owned()),
+                None => event.private_properties.remove(HASHES_PROPERTY),
+            };
+            let reconciliation = reconcile(&event, &post());
+            let patch = reconciliation.patch.unwrap();
+            assert_eq!(patch.summary.as_deref(), Some("Yoga"));
+            assert_eq!(
+                patch.private_properties.unwrap()[HASHES_PROPERTY],
+                signed(post()).private_properties[HASHES_PROPERTY]
+            );
+            assert!(reconciliation.conflicts.is_empty());
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use super::ServerState;
+use crate::ical::{calendar, upcoming_classes};
+
+pub const FEED_PATH: &str = "/calendar.ics";
+
+/// Calendar apps poll feeds often, the studios are asked at most this often.
+const CACHE_TTL: Duration = Duration::from_mins(5);
+
+#[derive(Default)]
+struct CachedFeed {
+    /// Last feed with all studios, `None` until one could be rendered
+    body: Option<String>,
+    /// When the studios were last asked, successfully or not
+    attempted_at: Option<Instant>,
+}
+
+/// iCalendar feed of the bookings, rendered on demand and cached.
+pub struct Feed {
+    token: String,
+    cached: Mutex<CachedFeed>,
+}
+
+impl Feed {
+    pub fn new(token: String) -> Self {
+        Feed {
+            token,
+            cached: Mutex::new(CachedFeed::default()),
+        }
+    }
+
+    /// Cached feed, rendered again once the last attempt is older than [`CACHE_TTL`].
+    /// Requests arriving during a render wait for it instead of asking the studios again.
+    /// `None` if no feed with all studios has been rendered yet.
+    async fn body(&self, state: &ServerState) -> Option<String> {
+        let mut cached = self.cached.lock().await;
+        if cached.attempted_at.is_some_and(|x| x.elapsed() < CACHE_TTL) {
+            return cached.body.clone();
+        }
+        let (classes, failed) = upcoming_classes(&state.clients, state.concurrency).await;
+        cached.attempted_at = Some(Instant::now());
+        if !failed.is_empty() {
+            // a feed missing a studio would make calendar apps delete its events
+            if cached.body.is_some() {
+                log::warn!("Serving the previous calendar feed as not all studios are available");
+            } else {
+                log::warn!("Not serving the calendar feed as not all studios are available");
+            }
+            return cached.body.clone();
+        }
+        let body = calendar(&classes, &state.config);
+        cached.body = Some(body.clone());
+        Some(body)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedQuery {
+    token: Option<String>,
+}
+
+pub async fn calendar_feed(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<FeedQuery>,
+) -> Response {
+    let Some(feed) = &state.feed else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !query
+        .token
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), feed.token.as_bytes()))
+    {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let Some(body) = feed.body(&state).await else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    (
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
+/// Takes the same time wherever the values differ, so the token can not be guessed byte by byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::api_clients::fake::FakeStudio;
+    use crate::models::SyncConfig;
+    use crate::server::notifications::Channels;
+
+    const TOKEN: &str = "secret";
+
+    /// Server with a feed of one studio, and the studio's request count and outage switch.
+    fn state() -> (Arc<ServerState>, Arc<AtomicUsize>, Arc<AtomicBool>) {
+        let studio = FakeStudio::new("First");
+        let requests = studio.user_requests.clone();
+        let unavailable = studio.unavailable.clone();
+        let state = ServerState {
+            google_client: None,
+            clients: vec![Box::new(studio)],
+            config: SyncConfig::default(),
+            concurrency: 1,
+            channels: Channels::default(),
+            journal_dir: "journal".into(),
+            feed: Some(Feed::new(TOKEN.to_owned())),
+            sync_requests: mpsc::unbounded_channel().0,
+        };
+        (Arc::new(state), requests, unavailable)
+    }
+
+    async fn get(state: &Arc<ServerState>, token: Option<&str>) -> StatusCode {
+        let query = FeedQuery {
+            token: token.map(ToOwned::to_owned),
+        };
+        calendar_feed(State(state.clone()), Query(query))
+            .await
+            .status()
+    }
+
+    /// Lets the cached feed expire.
+    async fn expire(state: &ServerState) {
+        let feed = state.feed.as_ref().unwrap();
+        feed.cached.lock().await.attempted_at = Instant::now().checked_sub(CACHE_TTL);
+    }
+
+    #[test]
+    fn compares_tokens() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+
+    #[tokio::test]
+    async fn requires_the_token() {
+        let (state, requests, _) = state();
+        assert_eq!(get(&state, None).await, StatusCode::NOT_FOUND);
+        assert_eq!(get(&state, Some("secre")).await, StatusCode::NOT_FOUND);
+        assert_eq!(get(&state, Some("SECRET")).await, StatusCode::NOT_FOUND);
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+        assert_eq!(get(&state, Some(TOKEN)).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn caches_the_feed() {
+        let (state, requests, _) = state();
+        assert_eq!(get(&state, Some(TOKEN)).await, StatusCode::OK);
+        assert_eq!(get(&state, Some(TOKEN)).await, StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        expire(&state).await;
+        assert_eq!(get(&state, Some(TOKEN)).await, StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn is_unavailable_without_a_complete_feed() {
+        let (state, requests, unavailable) = state();
+        unavailable.store(true, Ordering::SeqCst);
+        assert_eq!(
+            get(&state, Some(TOKEN)).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        // the studios are not asked again until the cache period has passed
+        assert_eq!(
+            get(&state, Some(TOKEN)).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        unavailable.store(false, Ordering::SeqCst);
+        expire(&state).await;
+        assert_eq!(get(&state, Some(TOKEN)).await, StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn serves_the_previous_feed_during_outages() {
+        let (state, requests, unavailable) = state();
+        assert_eq!(get(&state, Some(TOKEN)).await, StatusCode::OK);
+        unavailable.store(true, Ordering::SeqCst);
+        expire(&state).await;
+        assert_eq!(get(&state, Some(TOKEN)).await, StatusCode::OK);
+        assert_eq!(get(&state, Some(TOKEN)).await, StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        expire(&state).await;
+        assert_eq!(get(&state, Some(TOKEN)).await, StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+}
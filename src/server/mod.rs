@@ -0,0 +1,203 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use camino::Utf8PathBuf;
+use chrono::Utc;
+use color_eyre::{eyre::eyre, Result as AnyResult};
+use google_api::GoogleClient;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::api_clients::{models::UtcDateTime, StudioCRUD};
+use crate::backend::CalendarBackend;
+use crate::models::SyncConfig;
+use crate::settings::ServeArguments;
+use crate::sync::{calendar_ids, sync_calendars};
+
+pub mod feed;
+pub mod notifications;
+
+use feed::{Feed, FEED_PATH};
+use notifications::{Channels, NOTIFICATIONS_PATH};
+
+/// Google sends several notifications for a single change, wait for the rest before syncing.
+const SYNC_DEBOUNCE: Duration = Duration::from_secs(5);
+
+pub struct ServerState {
+    /// Watches calendars for changes, `None` unless syncing to Google Calendar
+    pub google_client: Option<GoogleClient>,
+    pub clients: Vec<Box<dyn StudioCRUD + Send + Sync>>,
+    pub config: SyncConfig,
+    pub concurrency: usize,
+    pub channels: Channels,
+    pub journal_dir: Utf8PathBuf,
+    pub feed: Option<Feed>,
+    sync_requests: UnboundedSender<String>,
+}
+
+impl ServerState {
+    /// Schedules a sync of the calendar.
+    pub fn request_sync(&self, calendar_id: String) {
+        if self.sync_requests.send(calendar_id).is_err() {
+            log::error!("Sync loop has stopped");
+        }
+    }
+}
+
+/// Runs scheduled syncs and, if `public_url` is set, watches the synced calendars
+/// and syncs them as soon as Google reports a change, other backends are only synced on schedule.
+/// With a feed token the bookings are served as an iCalendar feed as well,
+/// calendar sync is skipped when there is no `backend`.
+pub async fn serve(
+    backend: Option<Box<dyn CalendarBackend>>,
+    google_client: Option<GoogleClient>,
+    clients: Vec<Box<dyn StudioCRUD + Send + Sync>>,
+    config: SyncConfig,
+    concurrency: usize,
+    args: ServeArguments,
+) -> AnyResult<()> {
+    if backend.is_none() && args.feed_token.is_none() {
+        return Err(eyre!(
+            "Nothing to serve, set Google credentials, a CalDAV calendar or a feed token"
+        ));
+    }
+    let (sync_requests, receiver) = mpsc::unbounded_channel();
+    let state = Arc::new(ServerState {
+        google_client,
+        clients,
+        config,
+        concurrency,
+        channels: Channels::default(),
+        journal_dir: args.google.journal_dir,
+        feed: args.feed_token.map(Feed::new),
+        sync_requests,
+    });
+
+    if let (Some(google_client), Some(url)) = (&state.google_client, args.public_url) {
+        let address = format!("{}{NOTIFICATIONS_PATH}", url.as_str().trim_end_matches('/'));
+        for calendar_id in calendar_ids(google_client.calendar_id(), &state.config) {
+            if let Err(e) = state
+                .channels
+                .watch(google_client, &calendar_id, &address)
+                .await
+            {
+                log::error!("Could not watch calendar {calendar_id}: {e}");
+            }
+        }
+        tokio::spawn(notifications::renew_channels(
+            state.clone(),
+            google_client.clone(),
+            address,
+        ));
+    }
+    if let Some(backend) = backend {
+        let sync_interval = Duration::from_mins(args.sync_interval);
+        tokio::spawn(sync_loop(state.clone(), backend, receiver, sync_interval));
+    }
+    if state.feed.is_some() {
+        log::info!("Serving calendar feed at {FEED_PATH}?token=<feed token>");
+    }
+
+    let app = Router::new()
+        .route(NOTIFICATIONS_PATH, post(notifications::receive))
+        .route(FEED_PATH, get(feed::calendar_feed))
+        .with_state(state.clone());
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    log::info!("Listening on {}", args.listen);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await?;
+
+    if let Some(google_client) = &state.google_client {
+        state.channels.stop_all(google_client).await;
+    }
+    Ok(())
+}
+
+/// Syncs every `interval` and on request, requests arriving together are synced at once.
+/// Requested calendars are only synced if their managed events changed since the utility
+/// last synced them, so the notifications caused by its own writes do not start another sync.
+/// The sync of a changed calendar still diffs all of its events.
+async fn sync_loop(
+    state: Arc<ServerState>,
+    backend: Box<dyn CalendarBackend>,
+    mut requests: UnboundedReceiver<String>,
+    interval: Duration,
+) {
+    let mut timer = tokio::time::interval(interval);
+    let mut synced_at: HashMap<String, UtcDateTime> = HashMap::new();
+    loop {
+        let only = tokio::select! {
+            _ = timer.tick() => None,
+            Some(calendar_id) = requests.recv() => {
+                tokio::time::sleep(SYNC_DEBOUNCE).await;
+                let mut calendars = HashSet::from([calendar_id]);
+                while let Ok(calendar_id) = requests.try_recv() {
+                    calendars.insert(calendar_id);
+                }
+                Some(changed_calendars(&state, calendars, &synced_at).await)
+            }
+        };
+        if only.as_ref().is_some_and(HashSet::is_empty) {
+            log::debug!("Notified calendars have no changes since the last sync");
+            continue;
+        }
+        let started = Utc::now();
+        match sync_calendars(
+            &*backend,
+            &state.clients,
+            &state.config,
+            state.concurrency,
+            only.as_ref(),
+            &state.journal_dir,
+            false,
+        )
+        .await
+        {
+            Ok(()) => {
+                let synced =
+                    only.unwrap_or_else(|| calendar_ids(backend.calendar_id(), &state.config));
+                // the utility's own writes are older than this
+                let finished = Utc::now();
+                synced_at.extend(synced.into_iter().map(|x| (x, finished)));
+            }
+            Err(e) => log::error!("Sync started at {started} failed: {e}"),
+        }
+    }
+}
+
+/// Calendars whose managed events were changed after their last sync, calendars are kept
+/// if that can not be told.
+async fn changed_calendars(
+    state: &ServerState,
+    calendars: HashSet<String>,
+    synced_at: &HashMap<String, UtcDateTime>,
+) -> HashSet<String> {
+    let Some(google_client) = &state.google_client else {
+        return calendars;
+    };
+    let mut changed = HashSet::new();
+    for calendar_id in calendars {
+        let Some(since) = synced_at.get(&calendar_id) else {
+            changed.insert(calendar_id);
+            continue;
+        };
+        match notifications::changed_since(google_client, &calendar_id, *since).await {
+            Ok(false) => {}
+            Ok(true) => {
+                changed.insert(calendar_id);
+            }
+            Err(e) => {
+                log::warn!("Could not check calendar {calendar_id} for changes: {e}");
+                changed.insert(calendar_id);
+            }
+        }
+    }
+    changed
+}
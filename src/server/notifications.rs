@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use chrono::{Duration, Utc};
+use color_eyre::Result as AnyResult;
+use google_api::{
+    models::{
+        GoogleChannel, GoogleEventListParams, GoogleNotification, GoogleResourceState,
+        GoogleWatchPost,
+    },
+    GoogleClient,
+};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use super::ServerState;
+use crate::api_clients::models::{UtcDateTime, MANAGED_PROPERTY, MANAGED_VALUE};
+
+pub const NOTIFICATIONS_PATH: &str = "/notifications/google";
+
+/// Requested channel lifetime, Google caps it at a week for events.
+const CHANNEL_TTL: Duration = Duration::days(7);
+/// Channels expiring sooner than this are replaced.
+const RENEW_BEFORE: Duration = Duration::hours(1);
+const RENEWAL_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_mins(10);
+
+struct WatchedCalendar {
+    calendar_id: String,
+    channel: GoogleChannel,
+    token: String,
+}
+
+/// Push notification channels of the watched calendars, keyed by channel id.
+#[derive(Default)]
+pub struct Channels {
+    watched: Mutex<HashMap<String, WatchedCalendar>>,
+}
+
+impl Channels {
+    pub async fn watch(
+        &self,
+        google_client: &GoogleClient,
+        calendar_id: &str,
+        address: &str,
+    ) -> AnyResult<()> {
+        let token = Uuid::new_v4().to_string();
+        let channel = google_client
+            .with_calendar(calendar_id)?
+            .watch_events(&GoogleWatchPost {
+                id: Uuid::new_v4().to_string(),
+                address: address.to_owned(),
+                token: Some(token.clone()),
+                ttl: Some(CHANNEL_TTL),
+            })
+            .await?;
+        log::info!(
+            "Watching calendar {calendar_id} until {}",
+            channel.expiration.map_or("-".to_owned(), |x| x.to_string())
+        );
+        self.watched.lock().await.insert(
+            channel.id.clone(),
+            WatchedCalendar {
+                calendar_id: calendar_id.to_owned(),
+                channel,
+                token,
+            },
+        );
+        Ok(())
+    }
+
+    /// Calendar the notification is about, `None` for unknown channels or wrong tokens.
+    async fn calendar(&self, notification: &GoogleNotification) -> Option<String> {
+        self.watched
+            .lock()
+            .await
+            .get(&notification.channel_id)
+            .filter(|x| notification.token.as_ref() == Some(&x.token))
+            .map(|x| x.calendar_id.clone())
+    }
+
+    /// Replaces channels that are about to expire, the old channel is stopped
+    /// only after the new one is created so no changes are missed.
+    async fn renew_expiring(&self, google_client: &GoogleClient, address: &str) {
+        let expiring: Vec<(String, String)> = self
+            .watched
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, x)| {
+                x.channel
+                    .expiration
+                    .is_some_and(|expiration| expiration - Utc::now() < RENEW_BEFORE)
+            })
+            .map(|(id, x)| (id.clone(), x.calendar_id.clone()))
+            .collect();
+        for (channel_id, calendar_id) in expiring {
+            if let Err(e) = self.watch(google_client, &calendar_id, address).await {
+                log::error!("Could not renew channel for calendar {calendar_id}: {e}");
+                continue;
+            }
+            if let Some(old) = self.watched.lock().await.remove(&channel_id) {
+                stop(google_client, &old).await;
+            }
+        }
+    }
+
+    pub async fn stop_all(&self, google_client: &GoogleClient) {
+        for (_, watched) in self.watched.lock().await.drain() {
+            stop(google_client, &watched).await;
+        }
+    }
+}
+
+async fn stop(google_client: &GoogleClient, watched: &WatchedCalendar) {
+    if let Err(e) = google_client.stop_channel(&watched.channel).await {
+        log::warn!(
+            "Could not stop channel {} for calendar {}: {e}",
+            watched.channel.id,
+            watched.calendar_id
+        );
+    }
+}
+
+pub async fn renew_channels(state: Arc<ServerState>, google_client: GoogleClient, address: String) {
+    let mut timer = tokio::time::interval(RENEWAL_CHECK_INTERVAL);
+    loop {
+        timer.tick().await;
+        state
+            .channels
+            .renew_expiring(&google_client, &address)
+            .await;
+    }
+}
+
+/// Whether managed events of the calendar were changed, or deleted, after `since`.
+pub async fn changed_since(
+    google_client: &GoogleClient,
+    calendar_id: &str,
+    since: UtcDateTime,
+) -> AnyResult<bool> {
+    let events = google_client
+        .with_calendar(calendar_id)?
+        .list_events(
+            &GoogleEventListParams::new()
+                .updated_min(since)
+                .show_deleted(true)
+                .private_property(MANAGED_PROPERTY, MANAGED_VALUE),
+        )
+        .await?;
+    Ok(!events.is_empty())
+}
+
+/// Webhook receiving Google channel notifications.
+pub async fn receive(State(state): State<Arc<ServerState>>, headers: HeaderMap) -> StatusCode {
+    let Some(notification) = GoogleNotification::from_headers(&headers) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    let Some(calendar_id) = state.channels.calendar(&notification).await else {
+        log::warn!(
+            "Notification for unknown channel {}",
+            notification.channel_id
+        );
+        return StatusCode::NOT_FOUND;
+    };
+    match notification.state {
+        GoogleResourceState::Sync => {
+            log::debug!("Channel {} is ready", notification.channel_id);
+        }
+        GoogleResourceState::Exists | GoogleResourceState::NotExists => {
+            log::debug!("Calendar {calendar_id} has changed");
+            state.request_sync(calendar_id);
+        }
+    }
+    StatusCode::OK
+}
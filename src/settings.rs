@@ -2,11 +2,15 @@ use crate::api_clients::errors::ClientError;
 use crate::api_clients::holi_yoga::holi_client::HoliClient;
 use crate::api_clients::plastilin::plastilin_client::PlastilinClient;
 use crate::api_clients::StudioCRUD;
-use crate::models::SignUpConfig;
+use crate::backend::{caldav::CalDavClient, CalendarBackend};
+use crate::calendar::ensure_calendar;
+use crate::models::{SignUpConfig, SyncConfig};
+use crate::PRIMARY_CALENDAR_ID;
 use camino::Utf8PathBuf;
 use chrono::prelude::Local;
+use clap::builder::RangedU64ValueParser;
 use clap::{Args, Parser, Subcommand};
-use color_eyre::Result as AnyResult;
+use color_eyre::{eyre::eyre, Result as AnyResult};
 use email_address::EmailAddress;
 use fern::colors::{Color, ColoredLevelConfig};
 use fern::Dispatch;
@@ -15,6 +19,8 @@ use google_api::events_client::GoogleEventsClient;
 use google_api::jwt::JsonWebToken;
 use google_api::GoogleClient;
 use log::LevelFilter;
+use std::net::SocketAddr;
+use url::Url;
 use uuid::Uuid;
 
 const DEFAULT_HOLI_API_KEY: &str = "63b92ce0-3a63-4de5-8ee0-2756b62a0190";
@@ -22,6 +28,14 @@ const DEFAULT_HOLI_CLUB_ID: &str = "3dc77e1c-434c-11ea-bbc1-0050568bac14";
 
 const DEFAULT_PLASTILIN_CLUB_ID: &str = "1820";
 
+const DEFAULT_CALENDAR_NAME: &str = "Classes";
+
+const DEFAULT_LISTEN_ADDRESS: &str = "0.0.0.0:8080";
+
+const DEFAULT_JOURNAL_DIR: &str = "journal";
+
+const DEFAULT_EXPORT_PATH: &str = "classes.ics";
+
 #[derive(Debug, Parser)]
 pub struct Cli {
     #[command(subcommand)]
@@ -32,14 +46,95 @@ pub struct Cli {
     holi_yoga: Option<HoliYogaArguments>,
     #[arg(long, short = 'd', default_value = "false", env = "GCU__DEBUG")]
     debug: bool,
+    /// Maximum number of concurrent requests to studio APIs
+    #[arg(
+        long,
+        default_value = "4",
+        env = "GCU__CONCURRENCY",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub concurrency: usize,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum Commands {
     #[command(name = "sign-up", about = "Sign up for classes")]
-    SignUp(SignUpConfigPath),
+    SignUp(SignUpArguments),
     #[command(name = "sync", about = "Update classes in google calendar")]
-    SyncCalendars(GoogleArguments),
+    SyncCalendars(SyncArguments),
+    #[command(name = "serve", about = "Keep google calendar in sync")]
+    Serve(ServeArguments),
+    #[command(name = "rollback", about = "Undo the calendar changes of a sync run")]
+    Rollback(RollbackArguments),
+    #[command(
+        name = "export",
+        about = "Export upcoming classes to an iCalendar file"
+    )]
+    Export(ExportArguments),
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArguments {
+    /// Sync config, its summary and description templates are used for the events
+    #[command(flatten)]
+    pub config: SyncConfigPath,
+    /// Path of the written .ics file
+    #[arg(
+        long = "output",
+        short = 'o',
+        env = "GCU__EXPORT_PATH",
+        default_value = DEFAULT_EXPORT_PATH
+    )]
+    pub output: Utf8PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct SyncArguments {
+    #[command(flatten)]
+    pub google: GoogleArguments,
+    /// Sync even if more events would be deleted than the sync config limits allow
+    #[arg(long = "force")]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RollbackArguments {
+    #[command(flatten)]
+    pub google: GoogleArguments,
+    /// Id of the sync run, as logged by the sync
+    pub run_id: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArguments {
+    #[command(flatten)]
+    pub google: GoogleArguments,
+    /// Address the server listens on
+    #[arg(long = "listen", env = "GCU__LISTEN", default_value = DEFAULT_LISTEN_ADDRESS)]
+    pub listen: SocketAddr,
+    /// Public HTTPS url of the server, calendar changes are pushed to it by Google if set
+    #[arg(long = "public-url", env = "GCU__PUBLIC_URL")]
+    pub public_url: Option<Url>,
+    /// Minutes between scheduled syncs
+    #[arg(
+        long = "sync-interval",
+        env = "GCU__SYNC_INTERVAL",
+        default_value = "60",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub sync_interval: u64,
+    /// Secret token of the iCalendar feed, `/calendar.ics?token=<token>` is served if set
+    #[arg(long = "feed-token", env = "GCU__FEED_TOKEN")]
+    pub feed_token: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SignUpArguments {
+    #[command(flatten)]
+    pub config: SignUpConfigPath,
+    /// Google credentials, needed to check free/busy time before signing up
+    #[command(flatten)]
+    pub google: Option<GoogleCredentials>,
 }
 
 #[derive(Debug, Args)]
@@ -59,18 +154,89 @@ impl SignUpConfigPath {
 
 #[derive(Debug, Args)]
 pub struct GoogleArguments {
+    /// Mandatory for everything but serving the calendar feed
+    #[command(flatten)]
+    pub credentials: Option<GoogleCredentials>,
+    /// Google calendar id (usually your email address),
+    /// a dedicated calendar is created for the service account if not set
+    #[arg(long = "calendar-id", env = "GCU__GOOGLE_CALENDAR_ID")]
+    pub calendar_id: Option<String>,
+    /// Name of the calendar created when `--calendar-id` is not set
+    #[arg(
+        long = "calendar-name",
+        env = "GCU__GOOGLE_CALENDAR_NAME",
+        default_value = DEFAULT_CALENDAR_NAME
+    )]
+    pub calendar_name: String,
+    /// Email address the created calendar is shared with
+    #[arg(long = "share-with", env = "GCU__GOOGLE_SHARE_WITH")]
+    pub share_with: Option<EmailAddress>,
+    #[command(flatten)]
+    pub caldav: Option<CalDavArguments>,
+    #[command(flatten)]
+    pub config: SyncConfigPath,
+    /// Directory the events changed by each sync run are saved to, for rollbacks
+    #[arg(
+        long = "journal-dir",
+        env = "GCU__JOURNAL_DIR",
+        default_value = DEFAULT_JOURNAL_DIR
+    )]
+    pub journal_dir: Utf8PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct SyncConfigPath {
+    /// Path to sync config
+    #[arg(long = "sync-config", env = "GCU__SYNC_CONFIG")]
+    pub config_path: Option<Utf8PathBuf>,
+}
+
+impl SyncConfigPath {
+    pub fn parse(&self) -> AnyResult<SyncConfig> {
+        Ok(match &self.config_path {
+            Some(path) => serde_yaml::from_reader::<_, SyncConfig>(std::fs::File::open(path)?)?,
+            None => SyncConfig::default(),
+        })
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct GoogleCredentials {
     /// Email address of service account
-    #[arg(long = "email", env = "GCU__GOOGLE_EMAIL")]
+    #[arg(long = "email", env = "GCU__GOOGLE_EMAIL", required = false)]
     pub sa_email: EmailAddress,
     /// Service account private key id
-    #[arg(long = "kid", env = "GCU__GOOGLE_KEY_ID")]
+    #[arg(long = "kid", env = "GCU__GOOGLE_KEY_ID", required = false)]
     pub key_id: String,
     /// Path to service account private key
-    #[arg(long = "private-key", env = "GCU__GOOGLE_PRIVATE_KEY")]
+    #[arg(
+        long = "private-key",
+        env = "GCU__GOOGLE_PRIVATE_KEY",
+        required = false
+    )]
     pub private_key: Utf8PathBuf,
-    /// Google calendar id (usually your email address)
-    #[arg(long = "calendar-id", env = "GCU__GOOGLE_CALENDAR_ID")]
-    pub calendar_id: String,
+}
+
+/// CalDAV calendar used instead of Google Calendar when set.
+#[derive(Debug, Args)]
+pub struct CalDavArguments {
+    /// CalDAV calendar collection url
+    #[arg(long = "caldav-url", env = "GCU__CALDAV_URL", required = false)]
+    pub url: Url,
+    /// CalDAV username
+    #[arg(
+        long = "caldav-username",
+        env = "GCU__CALDAV_USERNAME",
+        required = false
+    )]
+    pub username: String,
+    /// CalDAV password, usually an app password
+    #[arg(
+        long = "caldav-password",
+        env = "GCU__CALDAV_PASSWORD",
+        required = false
+    )]
+    pub password: String,
 }
 
 #[derive(Debug, Args)]
@@ -81,7 +247,7 @@ pub struct HoliYogaArguments {
     /// Holi Yoga password
     #[arg(id = "holi-password", env = "GCU__HOLI_PASSWORD", required = false)]
     password: String,
-    /// Holi Yoga api key (api_key in request forms)
+    /// Holi Yoga api key (`api_key` in request forms)
     #[arg(
         id = "holi-api-key",
         env = "GCU__HOLI_API_KEY",
@@ -97,6 +263,13 @@ pub struct HoliYogaArguments {
         required = false,
     )]
     club_id: Uuid,
+    /// Holi Yoga booking link added to events, `{id}` is replaced with the class id
+    #[arg(
+        id = "holi-booking-url",
+        long = "holi-booking-url",
+        env = "GCU__HOLI_BOOKING_URL"
+    )]
+    booking_url: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -113,14 +286,86 @@ pub struct PlastilinArguments {
         required = false
     )]
     club_id: u16,
+    /// Plastilin booking link added to events, `{id}` is replaced with the class id
+    #[arg(
+        id = "plastilin-booking-url",
+        long = "plastilin-booking-url",
+        env = "GCU__PLASTILIN_BOOKING_URL"
+    )]
+    booking_url: Option<String>,
 }
 
 impl GoogleArguments {
-    pub async fn client(&self) -> Result<GoogleClient, GoogleClientError> {
+    pub fn sync_config(&self) -> AnyResult<SyncConfig> {
+        self.config.parse()
+    }
+
+    /// Client of the configured calendar, or of the service account's primary calendar.
+    /// Never creates calendars, calendars of past runs are known from the journal.
+    pub async fn client(&self) -> AnyResult<GoogleClient> {
+        let client = self.primary_client().await?;
+        Ok(match &self.calendar_id {
+            Some(id) => client.with_calendar(id)?,
+            None => client,
+        })
+    }
+
+    /// Client of the calendar classes are synced to, the calendar is created and shared
+    /// on the first sync when `--calendar-id` is not set.
+    pub async fn sync_client(&self) -> AnyResult<GoogleClient> {
+        let client = self.primary_client().await?;
+        let calendar_id = match &self.calendar_id {
+            Some(id) => id.clone(),
+            None => ensure_calendar(&client, &self.calendar_name, self.share_with.as_ref()).await?,
+        };
+        Ok(client.with_calendar(&calendar_id)?)
+    }
+
+    async fn primary_client(&self) -> AnyResult<GoogleClient> {
+        Ok(self
+            .credentials
+            .as_ref()
+            .ok_or_else(|| eyre!("Google credentials are not set"))?
+            .client(PRIMARY_CALENDAR_ID)
+            .await?)
+    }
+
+    /// Calendar backend classes are synced to, CalDAV if it is configured.
+    pub async fn sync_backend(&self) -> AnyResult<Box<dyn CalendarBackend>> {
+        Ok(match self.caldav_client()? {
+            Some(caldav) => Box::new(caldav),
+            None => Box::new(self.sync_client().await?),
+        })
+    }
+
+    /// Backend for undoing past runs, see [`GoogleArguments::client`].
+    pub async fn backend(&self) -> AnyResult<Box<dyn CalendarBackend>> {
+        Ok(match self.caldav_client()? {
+            Some(caldav) => Box::new(caldav),
+            None => Box::new(self.client().await?),
+        })
+    }
+
+    fn caldav_client(&self) -> AnyResult<Option<CalDavClient>> {
+        self.caldav
+            .as_ref()
+            .map(|caldav| {
+                CalDavClient::new(
+                    &caldav.url,
+                    caldav.username.clone(),
+                    caldav.password.clone(),
+                )
+            })
+            .transpose()
+    }
+}
+
+impl GoogleCredentials {
+    pub async fn client(&self, calendar_id: &str) -> Result<GoogleClient, GoogleClientError> {
         let private_key: String =
             std::fs::read_to_string(&self.private_key).expect("Unable to read file");
         let jwt = JsonWebToken::build(self.key_id.clone(), self.sa_email.clone(), private_key)?;
-        let events_client = GoogleEventsClient::new(&self.calendar_id)?;
+        let events_client = GoogleEventsClient::new(calendar_id)?;
         GoogleClient::new(events_client, jwt).await
     }
 }
@@ -132,6 +377,7 @@ impl HoliYogaArguments {
             self.club_id,
             self.username.clone(),
             self.password.clone(),
+            self.booking_url.clone(),
         )
         .await
     }
@@ -139,7 +385,7 @@ impl HoliYogaArguments {
 
 impl PlastilinArguments {
     pub fn client(&self) -> Result<PlastilinClient, ClientError> {
-        PlastilinClient::new(&self.token, self.club_id)
+        PlastilinClient::new(&self.token, self.club_id, self.booking_url.clone())
     }
 }
 
@@ -188,7 +434,7 @@ impl Cli {
         if let Some(client) = self.plastilin_client()? {
             clients.push(Box::new(client));
         }
-        return Ok(clients);
+        Ok(clients)
     }
     async fn holi_client(&self) -> Result<Option<HoliClient>, ClientError> {
         Ok(match &self.holi_yoga {
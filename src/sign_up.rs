@@ -0,0 +1,221 @@
+use chrono::Duration;
+use color_eyre::Result as AnyResult;
+use futures::{stream, Future, StreamExt};
+use google_api::{models::GoogleBusyPeriod, GoogleClient};
+use tokio::sync::Semaphore;
+
+use crate::api_clients::{
+    errors::ClientError, models::Class, schedule_cache::ScheduleCache, StudioCRUD,
+};
+use crate::models::{ConflictAction, FreeBusyConfig, PotentialClass, SignUpConfig, UtcDateTime};
+
+/// Busy time from the calendars in the free/busy config.
+#[derive(Debug, Clone)]
+pub struct BusyTimes {
+    periods: Vec<GoogleBusyPeriod>,
+    action: ConflictAction,
+}
+
+impl BusyTimes {
+    pub async fn fetch(
+        google_client: &GoogleClient,
+        config: &FreeBusyConfig,
+        start: UtcDateTime,
+        end: UtcDateTime,
+    ) -> AnyResult<Self> {
+        let calendars = google_client
+            .query_free_busy(start, end, &config.calendars)
+            .await?;
+        let mut periods = Vec::new();
+        for (id, calendar) in calendars {
+            if !calendar.errors.is_empty() {
+                log::warn!(
+                    "Could not get busy time of calendar {id}: {}",
+                    calendar.errors.join(", ")
+                );
+            }
+            periods.extend(calendar.busy);
+        }
+        Ok(BusyTimes {
+            periods,
+            action: config.on_conflict,
+        })
+    }
+
+    fn conflict(&self, class: &Class) -> Option<&GoogleBusyPeriod> {
+        self.periods
+            .iter()
+            .find(|period| period.start < class.end && class.start < period.end)
+    }
+}
+
+/// Time range covering all classes in the sign-up config.
+pub fn sign_up_range(
+    config: &SignUpConfig,
+    clients: &[Box<dyn StudioCRUD + Send + Sync>],
+) -> Option<(UtcDateTime, UtcDateTime)> {
+    let starts: Vec<UtcDateTime> = clients
+        .iter()
+        .flat_map(|client| config.classes(&client.name()))
+        .map(|class| class.start)
+        .collect();
+    Some((
+        *starts.iter().min()?,
+        *starts.iter().max()? + Duration::days(1),
+    ))
+}
+
+pub async fn sign_up<T>(
+    client: &T,
+    config: &SignUpConfig,
+    cache: &ScheduleCache,
+    busy: Option<&BusyTimes>,
+    requests: &Semaphore,
+) where
+    T: StudioCRUD + ?Sized,
+{
+    let classes = config.classes(&client.name());
+    log::debug!("Classes in config: {:?}", classes);
+    if classes.is_empty() {
+        return;
+    }
+
+    // booked classes would conflict with their own events
+    let booked = match busy {
+        Some(_) => limited(requests, client.get_user_classes())
+            .await
+            .unwrap_or_else(|err| {
+                log::error!("Could not get {} user classes: {}", client.name(), err);
+                Vec::new()
+            }),
+        None => Vec::new(),
+    };
+    let booked = &booked;
+
+    stream::iter(classes)
+        .for_each_concurrent(None, |potential_class| async move {
+            limited(
+                requests,
+                sign_up_for_class(client, cache, busy, booked, &potential_class),
+            )
+            .await;
+        })
+        .await;
+}
+
+/// Runs `request` once one of the shared request slots is free.
+async fn limited<F: Future>(requests: &Semaphore, request: F) -> F::Output {
+    // the semaphore is never closed
+    let _permit = requests
+        .acquire()
+        .await
+        .expect("request semaphore is closed");
+    request.await
+}
+
+async fn sign_up_for_class<T>(
+    client: &T,
+    cache: &ScheduleCache,
+    busy: Option<&BusyTimes>,
+    booked: &[Class],
+    potential_class: &PotentialClass,
+) where
+    T: StudioCRUD + ?Sized,
+{
+    match cache.list_day_classes(client, &potential_class.start).await {
+        Ok(classes) => {
+            if let Some(class) = classes.iter().find(|x| potential_class.eq(x)) {
+                if booked.iter().any(|x| x.id == class.id) {
+                    log::info!("Already signed up for {} class {}", client.name(), class);
+                    return;
+                }
+                if let Some((period, action)) =
+                    busy.and_then(|x| x.conflict(class).map(|period| (period, x.action)))
+                {
+                    log::warn!(
+                        "{} class {} conflicts with busy time {} - {}",
+                        client.name(),
+                        class,
+                        period.start,
+                        period.end
+                    );
+                    if action == ConflictAction::Skip {
+                        return;
+                    }
+                }
+                match client.sign_up_for_class(class).await {
+                    Ok(()) => {}
+                    Err(ClientError::AlreadyExists { id: _ }) => {
+                        log::info!("Already signed up for {} class {}", client.name(), class);
+                    }
+                    Err(ClientError::ClassFull { id: _ }) => {
+                        log::warn!("{} class {} is full", client.name(), class);
+                    }
+                    Err(ClientError::BookingClosed { id: _ }) => {
+                        log::warn!("Booking for {} class {} is closed", client.name(), class);
+                    }
+                    Err(e) => {
+                        log::error!(
+                            "Could not sign up for {} class {}: {}",
+                            client.name(),
+                            class,
+                            e
+                        );
+                    }
+                }
+            } else {
+                log::error!("Could not find {} class {}", client.name(), potential_class);
+            }
+        }
+        Err(err) => {
+            log::error!(
+                "Could not get {} classes for {}: {}",
+                client.name(),
+                potential_class.start,
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::api_clients::fake::{FakeStudio, InFlight};
+
+    const CONFIG: &str = "
+classes:
+  first: &week
+    - { name: Yoga, weekday: Mon, startTime: 10:00 }
+    - { name: Yoga, weekday: Tue, startTime: 10:00 }
+    - { name: Yoga, weekday: Wed, startTime: 10:00 }
+    - { name: Yoga, weekday: Thu, startTime: 10:00 }
+    - { name: Yoga, weekday: Fri, startTime: 10:00 }
+  second: *week
+";
+
+    #[tokio::test]
+    async fn shares_the_request_limit_across_studios() {
+        let config: SignUpConfig = serde_yaml::from_str(CONFIG).unwrap();
+        let in_flight = Arc::new(InFlight::default());
+        let studios = ["First", "Second"].map(|name| FakeStudio {
+            in_flight: in_flight.clone(),
+            ..FakeStudio::new(name)
+        });
+        let cache = ScheduleCache::new();
+        let requests = Semaphore::new(2);
+        stream::iter(&studios)
+            .for_each_concurrent(None, |studio| {
+                sign_up(studio, &config, &cache, None, &requests)
+            })
+            .await;
+        for studio in &studios {
+            assert_eq!(studio.day_requests.load(Ordering::SeqCst), 5);
+            assert_eq!(studio.sign_ups.load(Ordering::SeqCst), 5);
+        }
+        assert_eq!(in_flight.max.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,900 @@
+use std::collections::{HashMap, HashSet};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use chrono::Utc;
+use color_eyre::{eyre::eyre, Result as AnyResult};
+use futures::{stream, StreamExt};
+use google_api::{
+    batch::GoogleEventBatch,
+    models::{GoogleEvent, GoogleEventPatch, GoogleEventPost},
+};
+
+use crate::api_clients::{
+    errors::ClientError,
+    models::{
+        Class, EventKind, UtcDateTime, CANCELLED_PREFIX, CANCELLED_PROPERTY, CLASS_ID_PROPERTY,
+        DELETED_PROPERTY, HASHES_PROPERTY, MANAGED_VALUE, STUDIO_PROPERTY,
+    },
+    schedule_cache::ScheduleCache,
+    StudioCRUD,
+};
+use crate::backend::CalendarBackend;
+use crate::journal::{
+    migrated_calendars, record_migrated_calendar, record_synced_calendars, synced_calendars,
+    Journal, JournalEntry,
+};
+use crate::models::{SafetyLimits, StudioCancelAction, SyncConfig};
+use crate::ownership;
+
+/// Deleting one or two events is fine even if they are all the calendar has.
+const MIN_DELETIONS_CHECKED_BY_SHARE: usize = 3;
+
+/// Changes needed to bring the calendar in line with studio bookings.
+#[derive(Debug, Default)]
+struct SyncPlan {
+    additions: Vec<GoogleEventPost>,
+    updates: Vec<(GoogleEvent, GoogleEventPatch)>,
+    deletions: Vec<GoogleEvent>,
+    /// Bookings whose events were deleted by the user, only filled in two-way mode.
+    cancellations: Vec<Class>,
+}
+
+impl SyncPlan {
+    fn is_empty(&self) -> bool {
+        self.additions.is_empty()
+            && self.updates.is_empty()
+            && self.deletions.is_empty()
+            && self.cancellations.is_empty()
+    }
+}
+
+/// Ids of all calendars classes are synced to.
+pub fn calendar_ids(default_calendar_id: &str, config: &SyncConfig) -> HashSet<String> {
+    config
+        .calendars()
+        .into_iter()
+        .cloned()
+        .chain([default_calendar_id.to_owned()])
+        .collect()
+}
+
+/// Syncs booked classes to the backend's calendars. Classes are routed to calendars by the sync config,
+/// each calendar is diffed on its own and only events marked as managed by the utility are touched.
+/// `only` limits the sync to some of the calendars, e.g. the ones Google reported changes in.
+/// In two-way mode deleting a class event cancels its booking, see [`get_class_status`].
+/// Calendars whose sync would delete more events than the configured limits are skipped
+/// unless `force` is set.
+pub async fn sync_calendars(
+    backend: &dyn CalendarBackend,
+    clients: &[Box<dyn StudioCRUD + Send + Sync>],
+    config: &SyncConfig,
+    concurrency: usize,
+    only: Option<&HashSet<String>>,
+    journal_dir: &Utf8Path,
+    force: bool,
+) -> AnyResult<()> {
+    check_colors(backend, config).await;
+    let now = Utc::now();
+    let (mut classes, failed_studios) = get_all_classes(clients, concurrency).await;
+    // the studios return bookings regardless of the window, the rest are not listed from the calendar.
+    // Classes in progress and their travel events are still listed, so they are kept as well
+    classes.retain(|class| {
+        let (start, end) = class.span(&config.studio(&class.studio));
+        config.window.overlaps(now, start, end)
+    });
+    let default_calendar = backend.calendar_id().to_owned();
+    let configured = calendar_ids(&default_calendar, config);
+    let former: HashSet<String> = synced_calendars(journal_dir, backend.name())?
+        .difference(&configured)
+        .cloned()
+        .collect();
+
+    // calendars that no longer get any classes, including ones dropped from the config
+    // since an earlier run, still need their stale events deleted
+    let mut routes: HashMap<String, Vec<Class>> = configured
+        .iter()
+        .chain(&former)
+        .map(|calendar_id| (calendar_id.clone(), Vec::new()))
+        .collect();
+    for class in classes {
+        let calendar_id = config
+            .studio(&class.studio)
+            .calendar(&class.name)
+            .unwrap_or_else(|| default_calendar.clone());
+        routes.entry(calendar_id).or_default().push(class);
+    }
+    if let Some(only) = only {
+        routes.retain(|calendar_id, _| only.contains(calendar_id));
+    }
+
+    let run = SyncRun {
+        journal: Journal::new(journal_dir),
+        journal_dir: journal_dir.to_owned(),
+        migrated: migrated_calendars(journal_dir)?,
+        failed_studios,
+        force,
+    };
+    // former calendars are forgotten once a sync finds nothing left to delete in them
+    let mut remembered = configured.clone();
+    remembered.extend(former.iter().cloned());
+    for (calendar_id, classes) in routes {
+        let result = match backend.with_calendar(&calendar_id) {
+            Ok(client) => sync_calendar(&*client, clients, classes, config, &run).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(calendar_changed) => {
+                // events of failed studios are left alone, so they may still be there
+                if !calendar_changed
+                    && run.failed_studios.is_empty()
+                    && !configured.contains(&calendar_id)
+                {
+                    remembered.remove(&calendar_id);
+                }
+            }
+            Err(e) => log::error!("Could not sync calendar {calendar_id}: {e}"),
+        }
+    }
+    record_synced_calendars(journal_dir, backend.name(), &remembered)?;
+    // a run may have changed a calendar even if its sync failed halfway
+    if run.journal.is_written() {
+        log::info!(
+            "Changes are saved as run {0}, undo them with `rollback {0}`",
+            run.journal.run_id()
+        );
+    }
+    Ok(())
+}
+
+/// State shared by the calendars synced in one run.
+struct SyncRun {
+    journal: Journal,
+    journal_dir: Utf8PathBuf,
+    /// Calendars without events from before the managed mark
+    migrated: HashSet<String>,
+    /// Studios whose bookings could not be fetched, their events are not deleted
+    failed_studios: HashSet<String>,
+    force: bool,
+}
+
+async fn sync_calendar(
+    backend: &dyn CalendarBackend,
+    clients: &[Box<dyn StudioCRUD + Send + Sync>],
+    classes: Vec<Class>,
+    config: &SyncConfig,
+    run: &SyncRun,
+) -> AnyResult<bool> {
+    let now = Utc::now();
+    let google_classes = list_class_events(backend, &classes, config, run, now).await?;
+    let mut plan = get_class_status(&google_classes, classes, clients, config);
+    keep_failed_studios(&mut plan, &run.failed_studios);
+    if config.on_studio_cancel == StudioCancelAction::Mark {
+        mark_studio_cancellations(&mut plan, clients).await;
+    }
+    if !run.force {
+        check_limits(&plan, &google_classes, &config.limits)?;
+    }
+
+    if plan.is_empty() {
+        return Ok(false);
+    }
+    // snapshots are saved before touching the calendar, a run that can not be undone is not started
+    let mut entry = JournalEntry {
+        calendar_id: backend.calendar_id().to_owned(),
+        time: now,
+        created: Vec::new(),
+        updated: plan
+            .updates
+            .iter()
+            .map(|(x, _)| x.snapshot.clone())
+            .collect(),
+        deleted: plan.deletions.iter().map(|x| x.snapshot.clone()).collect(),
+    };
+    run.journal.write(&entry)?;
+    cancel_bookings(clients, &plan.cancellations).await;
+    stamp_deletions(backend, &mut plan.deletions).await;
+    let mut batch = GoogleEventBatch::new();
+    for post in &plan.additions {
+        batch = batch.insert(post.clone());
+    }
+    for (event, patch) in &plan.updates {
+        batch = batch.patch(&event.id, patch.clone());
+    }
+    for event in &plan.deletions {
+        batch = batch.delete(&event.id);
+    }
+    let mut results = backend.execute_batch(&batch).await.into_iter();
+
+    for (post, result) in plan.additions.iter().zip(results.by_ref()) {
+        match result {
+            Err(e) => log::error!(
+                "Could not create event {} at {}: {}",
+                post.summary,
+                post.start,
+                e
+            ),
+            Ok(event) => {
+                entry.created.extend(event.map(|x| x.id));
+                log::info!("Added {} at {} to calendar", post.summary, post.start);
+            }
+        }
+    }
+    run.journal.write(&entry)?;
+    for ((event, _), result) in plan.updates.iter().zip(results.by_ref()) {
+        if let Err(e) = result {
+            log::error!(
+                "Could not update event {} at {}: {}",
+                event.summary(),
+                event.start(),
+                e
+            );
+        } else {
+            log::info!("Updated event {} at {}", event.summary(), event.start(),);
+        }
+    }
+    for (event, result) in plan.deletions.iter().zip(results) {
+        if let Err(e) = result {
+            log::error!(
+                "Could not delete event {} at {}: {}",
+                event.summary(),
+                event.start(),
+                e
+            );
+        } else {
+            log::info!("Deleted event {} at {}", event.summary(), event.start(),);
+        }
+    }
+    Ok(true)
+}
+
+/// Managed events in the sync window. Until the calendar is migrated, events of booked classes
+/// created before events were marked as managed are added too: they are synced like managed
+/// events, which marks them, while unmarked events of other classes are left alone.
+/// The calendar counts as migrated once none are left.
+async fn list_class_events(
+    backend: &dyn CalendarBackend,
+    classes: &[Class],
+    config: &SyncConfig,
+    run: &SyncRun,
+    now: UtcDateTime,
+) -> AnyResult<Vec<GoogleEvent>> {
+    let (start, end) = (config.window.start(now), config.window.end(now));
+    let mut events = backend
+        .list_managed_events(start, end, config.two_way.is_some())
+        .await?;
+    if run.migrated.contains(backend.calendar_id()) {
+        return Ok(events);
+    }
+    let unmarked: Vec<_> = backend
+        .list_unmarked_events(start, end)
+        .await?
+        .into_iter()
+        .filter(|event| {
+            !event.cancelled
+                && classes
+                    .iter()
+                    .any(|class| class.is_event(event, EventKind::Class))
+        })
+        .collect();
+    if unmarked.is_empty() {
+        record_migrated_calendar(&run.journal_dir, backend.calendar_id())?;
+    } else {
+        log::info!(
+            "Marking {} events created by an earlier version as managed",
+            unmarked.len()
+        );
+        events.extend(unmarked);
+    }
+    Ok(events)
+}
+
+/// Booked classes of all studios, and the studios whose bookings could not be fetched.
+pub async fn get_all_classes(
+    clients: &[Box<dyn StudioCRUD + Send + Sync>],
+    concurrency: usize,
+) -> (Vec<Class>, HashSet<String>) {
+    // collected first so the stream type does not hold the closure and stays `Send`
+    let requests: Vec<_> = clients
+        .iter()
+        .map(|client| async move { (client, client.get_user_classes().await) })
+        .collect();
+    let responses: Vec<_> = stream::iter(requests)
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+    let mut classes = Vec::new();
+    let mut failed = HashSet::new();
+    for (client, response) in responses {
+        if let Ok(mut value) = response {
+            if value.is_empty() {
+                log::info!("Nothing to do for {}", client.name());
+                continue;
+            }
+            classes.append(&mut value);
+        } else {
+            log::error!(
+                "Could not get {} user classes, leaving its events as they are",
+                client.name()
+            );
+            failed.insert(client.name());
+        }
+    }
+    (classes, failed)
+}
+
+/// Leaves the events of studios whose bookings could not be fetched, their bookings are unknown.
+fn keep_failed_studios(plan: &mut SyncPlan, failed_studios: &HashSet<String>) {
+    plan.deletions.retain(|event| {
+        !event
+            .private_properties
+            .get(STUDIO_PROPERTY)
+            .is_some_and(|studio| failed_studios.contains(studio))
+    });
+}
+
+/// Refuses plans deleting more events or cancelling more bookings than the limits allow,
+/// e.g. when a studio returns no bookings because of an outage or a changed response format,
+/// or when the calendar was cleared.
+fn check_limits(
+    plan: &SyncPlan,
+    google_classes: &[GoogleEvent],
+    limits: &SafetyLimits,
+) -> AnyResult<()> {
+    let cancellations = plan.cancellations.len();
+    if cancellations > limits.max_cancellations {
+        return Err(eyre!(
+            "Refusing to cancel {cancellations} bookings, the limit is {}, run with --force to cancel them anyway",
+            limits.max_cancellations
+        ));
+    }
+    let deletions = plan.deletions.len();
+    let managed = google_classes.iter().filter(|x| !x.cancelled).count();
+    if deletions > limits.max_deletions {
+        return Err(eyre!(
+            "Refusing to delete {deletions} events, the limit is {}, run with --force to delete them anyway",
+            limits.max_deletions
+        ));
+    }
+    if deletions >= MIN_DELETIONS_CHECKED_BY_SHARE
+        && deletions * 100 > managed * usize::from(limits.max_deleted_percent)
+    {
+        return Err(eyre!(
+            "Refusing to delete {deletions} of {managed} events, the limit is {}%, run with --force to delete them anyway",
+            limits.max_deleted_percent
+        ));
+    }
+    Ok(())
+}
+
+fn studio_client<'a>(
+    clients: &'a [Box<dyn StudioCRUD + Send + Sync>],
+    studio: &str,
+) -> Option<&'a (dyn StudioCRUD + Send + Sync)> {
+    clients
+        .iter()
+        .find(|x| x.name() == studio)
+        .map(AsRef::as_ref)
+}
+
+/// Cancels bookings of the classes whose events were deleted.
+async fn cancel_bookings(clients: &[Box<dyn StudioCRUD + Send + Sync>], classes: &[Class]) {
+    for class in classes {
+        let Some(client) = studio_client(clients, &class.studio) else {
+            log::error!("Could not cancel {class}: unknown studio {}", class.studio);
+            continue;
+        };
+        match client.cancel_class(class).await {
+            Ok(()) => log::info!("Cancelled booking for {class} as its event was deleted"),
+            Err(e) => log::error!("Could not cancel booking for {class}: {e}"),
+        }
+    }
+}
+
+/// Matches studio classes with Google events.
+/// Classes without an event need to be added to Google Calendar,
+/// matched events whose content differs from the rendered class need to be updated
+/// (except for fields edited in Google Calendar, see [`ownership::reconcile`]),
+/// and events without a class need to be deleted from Google Calendar.
+///
+/// In two-way mode a class whose event was deleted by the user is cancelled once the grace
+/// period has passed, its travel events are deleted along with it. Until then the event is
+/// left deleted, and if the studio no longer allows cancelling, the event is restored.
+fn get_class_status(
+    google_classes: &[GoogleEvent],
+    studio_classes: Vec<Class>,
+    clients: &[Box<dyn StudioCRUD + Send + Sync>],
+    config: &SyncConfig,
+) -> SyncPlan {
+    let now = Utc::now();
+    let mut plan = SyncPlan::default();
+    let mut matched = vec![false; google_classes.len()];
+    for class in studio_classes {
+        let studio = config.studio(&class.studio);
+        let mut keep_deleted = false;
+        if let (Some(two_way), Some(deleted)) =
+            (&config.two_way, deleted_event(google_classes, &class))
+        {
+            let deadline = studio.cancellation_deadline().or_else(|| {
+                studio_client(clients, &class.studio).map(StudioCRUD::cancellation_deadline)
+            });
+            if deadline.is_none_or(|deadline| class.start - now < deadline) {
+                log::warn!("Event for {class} was deleted, but the booking can not be cancelled anymore, restoring it");
+            } else if deleted
+                .updated
+                .is_none_or(|updated| now - updated < two_way.grace_period())
+            {
+                log::info!("Event for {class} was deleted, the booking will be cancelled unless it is restored");
+                keep_deleted = true;
+            } else {
+                plan.cancellations.push(class);
+                continue;
+            }
+        }
+        for (kind, post) in class.to_google_posts(&studio) {
+            if keep_deleted && kind == EventKind::Class {
+                continue;
+            }
+            let index = google_classes
+                .iter()
+                .enumerate()
+                .position(|(index, google)| {
+                    !matched[index] && !google.cancelled && class.is_event(google, kind)
+                });
+            match index {
+                Some(index) => {
+                    matched[index] = true;
+                    let event = &google_classes[index];
+                    if is_marked_cancelled(event) {
+                        log::info!("{class} is booked again");
+                        plan.updates.push((event.clone(), restored(post)));
+                        continue;
+                    }
+                    let reconciliation = ownership::reconcile(event, &post);
+                    for field in reconciliation.conflicts {
+                        log::warn!(
+                            "{field} of {} at {} was edited in the calendar and changed by the studio, keeping the edit",
+                            post.summary,
+                            post.start
+                        );
+                    }
+                    if let Some(patch) = reconciliation.patch {
+                        plan.updates.push((event.clone(), patch));
+                    }
+                }
+                None => plan.additions.push(ownership::signed(post)),
+            }
+        }
+    }
+    plan.deletions = google_classes
+        .iter()
+        .zip(matched)
+        .filter(|(google, matched)| !matched && !is_kept(google, config, now))
+        .map(|(google, _)| google.clone())
+        .collect();
+    plan
+}
+
+/// Moves class events of bookings cancelled by the studio from deletions to updates marking them.
+/// A booking missing from the user's classes was cancelled by the studio if the class is gone
+/// from the schedule as well, otherwise the user has cancelled it and the event is deleted.
+async fn mark_studio_cancellations(
+    plan: &mut SyncPlan,
+    clients: &[Box<dyn StudioCRUD + Send + Sync>],
+) {
+    let cache = ScheduleCache::new();
+    let mut deletions = Vec::new();
+    for event in std::mem::take(&mut plan.deletions) {
+        match cancelled_by_studio(&event, clients, &cache).await {
+            Ok(true) => {
+                log::warn!(
+                    "Booked class {} at {} was cancelled by the studio",
+                    event.summary(),
+                    event.start()
+                );
+                plan.updates.push((event.clone(), marked_cancelled(&event)));
+            }
+            Ok(false) => deletions.push(event),
+            Err(e) => log::error!(
+                "Could not check whether {} at {} was cancelled by the studio, leaving it: {e}",
+                event.summary(),
+                event.start()
+            ),
+        }
+    }
+    plan.deletions = deletions;
+}
+
+async fn cancelled_by_studio(
+    event: &GoogleEvent,
+    clients: &[Box<dyn StudioCRUD + Send + Sync>],
+    cache: &ScheduleCache,
+) -> Result<bool, ClientError> {
+    let (Some(EventKind::Class), Some(class_id), Some(studio), Some(start)) = (
+        EventKind::of(event),
+        event.private_properties.get(CLASS_ID_PROPERTY),
+        event.private_properties.get(STUDIO_PROPERTY),
+        event.start.and_then(|x| x.date_time()),
+    ) else {
+        return Ok(false);
+    };
+    let Some(client) = studio_client(clients, studio) else {
+        return Ok(false);
+    };
+    if start < Utc::now() {
+        return Ok(false);
+    }
+    let schedule = cache.list_day_classes(client, &start).await?;
+    Ok(!schedule.iter().any(|x| &x.id == class_id))
+}
+
+/// Whether an event without a booking stays: already deleted, marked as cancelled
+/// by the studio, or kept as an attendance record.
+fn is_kept(event: &GoogleEvent, config: &SyncConfig, now: UtcDateTime) -> bool {
+    let has_started = event
+        .start
+        .and_then(|x| x.date_time())
+        .is_some_and(|start| start < now);
+    event.cancelled || is_marked_cancelled(event) || config.window.keep_past && has_started
+}
+
+fn is_marked_cancelled(event: &GoogleEvent) -> bool {
+    event
+        .private_properties
+        .get(CANCELLED_PROPERTY)
+        .map(String::as_str)
+        == Some("true")
+}
+
+/// Keeps the event as free time with a prefixed title. Dropping the stored hashes makes
+/// the utility own all fields again in case the class is booked again.
+fn marked_cancelled(event: &GoogleEvent) -> GoogleEventPatch {
+    let mut private_properties = event.private_properties.clone();
+    private_properties.insert(CANCELLED_PROPERTY.to_owned(), "true".to_owned());
+    private_properties.insert(HASHES_PROPERTY.to_owned(), String::new());
+    GoogleEventPatch {
+        summary: Some(format!("{CANCELLED_PREFIX}{}", event.summary())),
+        private_properties: Some(private_properties),
+        transparent: Some(true),
+        ..Default::default()
+    }
+}
+
+fn restored(post: GoogleEventPost) -> GoogleEventPatch {
+    let mut post = ownership::signed(post);
+    post.private_properties
+        .insert(CANCELLED_PROPERTY.to_owned(), "false".to_owned());
+    GoogleEventPatch {
+        transparent: Some(false),
+        ..(&post).into()
+    }
+}
+
+/// The class event if it was deleted by the user and the class has no other event,
+/// deletions made by the utility are ignored.
+fn deleted_event<'a>(google_classes: &'a [GoogleEvent], class: &Class) -> Option<&'a GoogleEvent> {
+    let mut events = google_classes.iter().filter(|google| {
+        class.is_event(google, EventKind::Class)
+            && !(google.cancelled && google.private_properties.contains_key(DELETED_PROPERTY))
+    });
+    let deleted = events.clone().find(|google| google.cancelled)?;
+    events.all(|google| google.cancelled).then_some(deleted)
+}
+
+/// Marks the events before deleting them, see [`deleted_event`].
+/// Events that could not be marked are not deleted.
+async fn stamp_deletions(backend: &dyn CalendarBackend, deletions: &mut Vec<GoogleEvent>) {
+    if deletions.is_empty() {
+        return;
+    }
+    let mut batch = GoogleEventBatch::new();
+    for event in deletions.iter() {
+        batch = batch.patch(&event.id, deletion_stamp(&event.private_properties));
+    }
+    let mut results = backend.execute_batch(&batch).await.into_iter();
+    deletions.retain(|event| match results.next() {
+        Some(Ok(_)) => true,
+        Some(Err(e)) => {
+            log::error!(
+                "Could not mark event {} at {} for deletion, not deleting it: {e}",
+                event.summary(),
+                event.start()
+            );
+            false
+        }
+        None => false,
+    });
+}
+
+/// Patch marking an event as deleted by the utility, keeping its other private properties.
+pub fn deletion_stamp(private_properties: &HashMap<String, String>) -> GoogleEventPatch {
+    let mut private_properties = private_properties.clone();
+    private_properties.insert(DELETED_PROPERTY.to_owned(), MANAGED_VALUE.to_owned());
+    GoogleEventPatch {
+        private_properties: Some(private_properties),
+        ..GoogleEventPatch::default()
+    }
+}
+
+/// Warns about configured colors that the backend does not know.
+async fn check_colors(backend: &dyn CalendarBackend, config: &SyncConfig) {
+    let colors = config.colors();
+    if colors.is_empty() {
+        return;
+    }
+    match backend.event_colors().await {
+        Ok(Some(available)) => {
+            for color in colors {
+                if !available.contains(color) {
+                    log::warn!("Unknown event color id {color}, valid ids are: {available:?}");
+                }
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Could not get {} colors: {e}", backend.name()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use google_api::models::GoogleEventSnapshot;
+
+    use super::*;
+    use crate::models::SyncWindow;
+
+    const TWO_WAY: &str = "
+twoWay:
+  gracePeriod: 30
+studios:
+  plastilin:
+    cancellationDeadline: 3
+";
+
+    fn config(yaml: &str) -> SyncConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn class(id: &str, start: UtcDateTime) -> Class {
+        Class {
+            id: id.to_owned(),
+            name: "Yoga".to_owned(),
+            studio: "Plastilin".to_owned(),
+            instructor: "Anna".to_owned(),
+            instructor_id: None,
+            location: None,
+            room: None,
+            booking_url: None,
+            start,
+            end: start + Duration::hours(1),
+        }
+    }
+
+    fn event(class: &Class, config: &SyncConfig) -> GoogleEvent {
+        let post = class.to_google_post(&config.studio(&class.studio));
+        GoogleEvent {
+            id: format!("event{}", class.id),
+            summary: Some(post.summary),
+            description: post.description,
+            location: post.location,
+            source_url: None,
+            start: Some(post.start),
+            end: Some(post.end),
+            creator_email: None,
+            cancelled: false,
+            updated: None,
+            private_properties: post.private_properties,
+            color_id: post.color_id,
+            reminders: post.reminders,
+            recurrence: None,
+            recurring_event_id: None,
+            original_start: None,
+            snapshot: GoogleEventSnapshot::default(),
+        }
+    }
+
+    fn deleted(mut event: GoogleEvent, ago: Duration) -> GoogleEvent {
+        event.cancelled = true;
+        event.updated = Some(Utc::now() - ago);
+        event
+    }
+
+    fn plan(events: &[GoogleEvent], classes: Vec<Class>, config: &SyncConfig) -> SyncPlan {
+        get_class_status(events, classes, &[], config)
+    }
+
+    #[test]
+    fn keeps_events_deleted_within_the_grace_period() {
+        let config = config(TWO_WAY);
+        let class = class("1", Utc::now() + Duration::days(1));
+        let mut without_time = deleted(event(&class, &config), Duration::zero());
+        without_time.updated = None;
+        for event in [
+            deleted(event(&class, &config), Duration::minutes(10)),
+            without_time,
+        ] {
+            let plan = plan(&[event], vec![class.clone()], &config);
+            assert!(plan.cancellations.is_empty());
+            assert!(plan.additions.is_empty());
+            assert!(plan.is_empty());
+        }
+    }
+
+    #[test]
+    fn cancels_bookings_after_the_grace_period() {
+        let config = config(TWO_WAY);
+        let class = class("1", Utc::now() + Duration::days(1));
+        let events = [deleted(event(&class, &config), Duration::minutes(31))];
+        let plan = plan(&events, vec![class], &config);
+        assert_eq!(plan.cancellations.len(), 1);
+        assert_eq!(plan.cancellations[0].id, "1");
+        assert!(plan.additions.is_empty());
+        assert!(plan.deletions.is_empty());
+    }
+
+    #[test]
+    fn restores_events_past_the_cancellation_deadline() {
+        let config = config(TWO_WAY);
+        let class = class("1", Utc::now() + Duration::hours(2));
+        let events = [deleted(event(&class, &config), Duration::hours(1))];
+        let plan = plan(&events, vec![class], &config);
+        assert!(plan.cancellations.is_empty());
+        assert_eq!(plan.additions.len(), 1);
+        assert_eq!(plan.additions[0].private_properties[CLASS_ID_PROPERTY], "1");
+    }
+
+    #[test]
+    fn ignores_events_deleted_by_the_sync() {
+        let config = config(TWO_WAY);
+        let class = class("1", Utc::now() + Duration::days(1));
+        let mut event = deleted(event(&class, &config), Duration::hours(1));
+        event
+            .private_properties
+            .insert(DELETED_PROPERTY.to_owned(), MANAGED_VALUE.to_owned());
+        let plan = plan(&[event], vec![class], &config);
+        assert!(plan.cancellations.is_empty());
+        assert_eq!(plan.additions.len(), 1);
+    }
+
+    #[test]
+    fn does_not_cancel_classes_with_a_live_event() {
+        let config = config(TWO_WAY);
+        let class = class("1", Utc::now() + Duration::days(1));
+        let mut live = event(&class, &config);
+        live.id = "duplicate".to_owned();
+        let events = [deleted(event(&class, &config), Duration::hours(1)), live];
+        let plan = plan(&events, vec![class], &config);
+        assert!(plan.cancellations.is_empty());
+        assert!(plan.additions.is_empty());
+        assert!(plan.deletions.is_empty());
+    }
+
+    fn events(count: usize) -> Vec<GoogleEvent> {
+        let config = SyncConfig::default();
+        let start = Utc::now() + Duration::days(1);
+        (0..count)
+            .map(|x| event(&class(&x.to_string(), start), &config))
+            .collect()
+    }
+
+    fn deleting(count: usize) -> SyncPlan {
+        SyncPlan {
+            deletions: events(count),
+            ..SyncPlan::default()
+        }
+    }
+
+    #[test]
+    fn limits_deletions_by_count() {
+        let limits = SafetyLimits::default();
+        let managed = events(100);
+        assert!(check_limits(&deleting(limits.max_deletions), &managed, &limits).is_ok());
+        assert!(check_limits(&deleting(limits.max_deletions + 1), &managed, &limits).is_err());
+    }
+
+    #[test]
+    fn limits_deletions_by_share() {
+        let limits = SafetyLimits::default();
+        // deleting all events is fine below the floor
+        let below = MIN_DELETIONS_CHECKED_BY_SHARE - 1;
+        assert!(check_limits(&deleting(below), &events(below), &limits).is_ok());
+        let floor = MIN_DELETIONS_CHECKED_BY_SHARE;
+        assert!(check_limits(&deleting(floor), &events(floor), &limits).is_err());
+        // 50% of 10 events
+        assert!(check_limits(&deleting(5), &events(10), &limits).is_ok());
+        assert!(check_limits(&deleting(6), &events(10), &limits).is_err());
+    }
+
+    #[test]
+    fn counts_only_live_events_as_managed() {
+        let limits = SafetyLimits::default();
+        assert!(check_limits(&deleting(0), &[], &limits).is_ok());
+        assert!(check_limits(&deleting(MIN_DELETIONS_CHECKED_BY_SHARE - 1), &[], &limits).is_ok());
+        assert!(check_limits(&deleting(MIN_DELETIONS_CHECKED_BY_SHARE), &[], &limits).is_err());
+        let cancelled: Vec<_> = events(10)
+            .into_iter()
+            .map(|x| deleted(x, Duration::hours(1)))
+            .collect();
+        assert!(check_limits(
+            &deleting(MIN_DELETIONS_CHECKED_BY_SHARE),
+            &cancelled,
+            &limits
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn keeps_events_of_failed_studios() {
+        let config = config("studios: { plastilin: { travel: { before: 30 } } }");
+        let start = Utc::now() + Duration::days(1);
+        let mut other = event(&class("2", start), &config);
+        other
+            .private_properties
+            .insert(STUDIO_PROPERTY.to_owned(), "Holi Yoga".to_owned());
+        let mut events = vec![other];
+        for (_, post) in class("1", start).to_google_posts(&config.studio("Plastilin")) {
+            let mut event = event(&class("1", start), &config);
+            event.private_properties = post.private_properties;
+            events.push(event);
+        }
+        let mut plan = plan(&events, Vec::new(), &config);
+        assert_eq!(plan.deletions.len(), 3);
+        keep_failed_studios(&mut plan, &HashSet::from(["Plastilin".to_owned()]));
+        assert_eq!(plan.deletions.len(), 1);
+        assert_eq!(plan.deletions[0].private_properties[CLASS_ID_PROPERTY], "2");
+    }
+
+    #[test]
+    fn keeps_classes_straddling_the_window() {
+        let config = config("studios: { plastilin: { travel: { before: 30, after: 30 } } }");
+        let studio = config.studio("Plastilin");
+        let window = SyncWindow::default();
+        let now = Utc::now();
+        let overlaps = |class: &Class| {
+            let (start, end) = class.span(&studio);
+            window.overlaps(now, start, end)
+        };
+        // in progress at the window start
+        assert!(overlaps(&class("1", now - Duration::minutes(30))));
+        // travel back ends after the window start
+        assert!(overlaps(&class("1", now - Duration::minutes(80))));
+        assert!(!overlaps(&class("1", now - Duration::minutes(90))));
+        // travel to starts before the window end
+        let end = window.end(now);
+        assert!(overlaps(&class("1", end + Duration::minutes(20))));
+        assert!(!overlaps(&class("1", end + Duration::minutes(30))));
+    }
+
+    #[test]
+    fn keeps_past_events_only_with_keep_past() {
+        let now = Utc::now();
+        let past = event(
+            &class("1", now - Duration::hours(2)),
+            &SyncConfig::default(),
+        );
+        let future = event(
+            &class("2", now + Duration::hours(2)),
+            &SyncConfig::default(),
+        );
+        let config = config("window: { keepPast: true }");
+        assert!(is_kept(&past, &config, now));
+        assert!(!is_kept(&future, &config, now));
+        let config = SyncConfig::default();
+        assert!(!is_kept(&past, &config, now));
+        assert!(!is_kept(&future, &config, now));
+    }
+
+    #[test]
+    fn limits_cancellations() {
+        let limits = SafetyLimits::default();
+        let start = Utc::now() + Duration::days(1);
+        let mut plan = SyncPlan {
+            cancellations: (0..limits.max_cancellations)
+                .map(|x| class(&x.to_string(), start))
+                .collect(),
+            ..SyncPlan::default()
+        };
+        assert!(check_limits(&plan, &[], &limits).is_ok());
+        plan.cancellations.push(class("extra", start));
+        assert!(check_limits(&plan, &[], &limits).is_err());
+    }
+}